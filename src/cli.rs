@@ -0,0 +1,230 @@
+//! Command-line mode (runs instead of the GUI when arguments are given)
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::Path;
+
+use mwc_vin_decoder::decoded::{DecodeSource, DecodedVin};
+use mwc_vin_decoder::export::{self, ExportFormat, JSON_SCHEMA};
+use mwc_vin_decoder::{
+    all_field_options, model_index_for_code, normalize_code, parse_vin_input, read_carparts,
+    unwrap_typed_value, VEHICLE_MODELS,
+};
+
+const USAGE: &str = "\
+Usage: mwc-vin-decoder [OPTIONS]
+
+Without options the GUI is started.
+
+Options:
+  --decode VIN          Decode VIN (plain, or one token per field separated by | or spaces)
+  --vin VIN             Same as --decode
+  --file PATH           Decode the VIN saved in a carparts.txt (or .txt.gz)
+  --format FORMAT       Output of --decode/--file: table (default), json, csv,
+                        wiki (MediaWiki table) or keyvalue (Field=Decoded lines);
+                        `--format json --help` prints the JSON layout
+  --notes TEXT          Note included in the table and JSON output of --decode
+  --list-codes [FIELD]  Print every field's code -> label mappings (or just FIELD's)
+  --template            Print a fillable Markdown spec sheet of every field
+  --console             Show log output in a console (Windows); also starts
+                        the GUI when given alone
+  -h, --help            Print this help";
+
+/// Handle command-line arguments, returns the exit code or `None` to start the GUI
+pub fn run(args: impl Iterator<Item = String>) -> Option<i32> {
+    let mut args: Vec<String> = args.collect();
+    let mut format = ExportFormat::Text;
+    if let Some(i) = args.iter().position(|a| a == "--format") {
+        let name = args.get(i + 1).cloned().unwrap_or_default();
+        let Some(chosen) = parse_format(&name) else {
+            eprintln!(
+                "Unknown format: {:?} (expected table, json, csv, wiki or keyvalue)",
+                name
+            );
+            return Some(2);
+        };
+        format = chosen;
+        args.drain(i..(i + 2).min(args.len()));
+    }
+    let mut notes = String::new();
+    if let Some(i) = args.iter().position(|a| a == "--notes") {
+        notes = args.get(i + 1).cloned().unwrap_or_default();
+        args.drain(i..(i + 2).min(args.len()));
+    }
+    let first = args.first()?;
+    let code = match first.as_str() {
+        "--decode" | "--vin" => match args.get(1) {
+            Some(vin) => decode(vin, format, &notes),
+            None => {
+                eprintln!("{} needs a VIN\n\n{}", first, USAGE);
+                2
+            }
+        },
+        "--file" => match args.get(1) {
+            Some(path) => decode_file(Path::new(path), format, &notes),
+            None => {
+                eprintln!("--file needs a path\n\n{}", USAGE);
+                2
+            }
+        },
+        "-h" | "--help" if format == ExportFormat::Json => {
+            println!("{}\n\nJSON output of --decode:\n{}", USAGE, JSON_SCHEMA);
+            0
+        }
+        "--list-codes" => list_codes(args.get(1).map(String::as_str)),
+        "--template" => {
+            print!("{}", export::render_template());
+            0
+        }
+        "-h" | "--help" => {
+            println!("{}", USAGE);
+            0
+        }
+        other => {
+            eprintln!("Unknown argument: {}\n\n{}", other, USAGE);
+            2
+        }
+    };
+    Some(code)
+}
+
+/// Take `--console` out of `args` and, when it was given, route stdout and
+/// stderr to the parent console or a new one. Windows release builds have no
+/// console otherwise; elsewhere the output already goes to the terminal.
+pub fn take_console_flag(args: &mut Vec<String>) {
+    let before = args.len();
+    args.retain(|a| a != "--console");
+    if args.len() != before {
+        attach_console();
+    }
+}
+
+#[cfg(windows)]
+fn attach_console() {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+        fn AllocConsole() -> i32;
+    }
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    // SAFETY: plain Win32 calls without pointers; failure leaves the process
+    // without a console, as before
+    unsafe {
+        if AttachConsole(ATTACH_PARENT_PROCESS) == 0 {
+            AllocConsole();
+        }
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
+/// Output format for a `--format` name
+fn parse_format(name: &str) -> Option<ExportFormat> {
+    match name {
+        "table" => Some(ExportFormat::Text),
+        "json" => Some(ExportFormat::Json),
+        "csv" => Some(ExportFormat::Csv),
+        "wiki" => Some(ExportFormat::Wiki),
+        "keyvalue" => Some(ExportFormat::KeyValue),
+        _ => None,
+    }
+}
+
+/// Decode a VIN and print it in `format` with `notes`, rendered like the GUI export
+fn decode(input: &str, format: ExportFormat, notes: &str) -> i32 {
+    let parsed = parse_vin_input(input, false, |code| model_index_for_code(code).unwrap_or(0));
+    let parsed = match parsed {
+        Ok(Some(parsed)) => parsed,
+        Ok(None) => {
+            eprintln!("Empty VIN");
+            return 2;
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    let map = VEHICLE_MODELS[parsed.model].tables();
+    let decoded = DecodedVin::new(parsed.structure, &map, |key| {
+        parsed.entries.get(key).map_or("", String::as_str)
+    })
+    .with_notes(notes)
+    .with_source(DecodeSource::Manual);
+    print!("{}", format.render(&decoded));
+    0
+}
+
+/// Decode the VIN section of a carparts.txt (the first vehicle when it has
+/// several) and print it like [`decode`]
+fn decode_file(path: &Path, format: ExportFormat, notes: &str) -> i32 {
+    let (result, _) = read_carparts(path);
+    let entries = match result {
+        Ok(mut vehicles) => vehicles.swap_remove(0),
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    let values: HashMap<&str, Cow<str>> = entries
+        .iter()
+        .map(|(k, v)| (k.as_str(), normalize_code(unwrap_typed_value(v).1, false)))
+        .collect();
+    let get_value = |key: &str| values.get(key).map_or("", |v| v.as_ref());
+    let model = &VEHICLE_MODELS[model_index_for_code(get_value("Model")).unwrap_or(0)];
+    let map = model.tables();
+    let decoded = DecodedVin::new(model.structure, &map, get_value)
+        .with_notes(notes)
+        .with_source(DecodeSource::File);
+    print!("{}", format.render(&decoded));
+    0
+}
+
+/// Print the decode tables of every model, optionally limited to one field
+/// (by key or display name)
+fn list_codes(field: Option<&str>) -> i32 {
+    let mut found = false;
+    for model in VEHICLE_MODELS {
+        let structure = model.structure;
+        let map = model.tables();
+        let selected: Vec<_> = all_field_options(structure, &map)
+            .into_iter()
+            .filter(|(key, _)| {
+                field.map_or(true, |name| {
+                    structure.iter().any(|f| {
+                        f.key == *key
+                            && (f.key.eq_ignore_ascii_case(name)
+                                || f.display.eq_ignore_ascii_case(name))
+                    })
+                })
+            })
+            .collect();
+        if selected.is_empty() {
+            continue;
+        }
+        found = true;
+
+        if VEHICLE_MODELS.len() > 1 {
+            println!("== {} (Model {}) ==\n", model.name, model.code);
+        }
+        for (key, codes) in selected {
+            let Some(vin_field) = structure.iter().find(|f| f.key == key) else {
+                continue;
+            };
+            println!("{} ({} char)", vin_field.display, vin_field.len);
+            if codes.is_empty() {
+                println!("  (free-form)");
+            }
+            for (code, label) in codes {
+                println!("  {:<4} {}", code, label);
+            }
+            println!();
+        }
+    }
+
+    if !found {
+        eprintln!("Unknown field: {}", field.unwrap_or_default());
+        return 2;
+    }
+    0
+}
@@ -0,0 +1,116 @@
+//! Typed codes for the stable, meaningful fields
+//!
+//! Colors, trims and other long or volatile tables stay in the [`DecodeMap`];
+//! these enums mirror the built-in tables of the fields whose codes are fixed,
+//! so callers can match exhaustively instead of comparing strings.
+//!
+//! [`DecodeMap`]: crate::DecodeMap
+
+/// A field whose known codes are a closed set
+pub trait FieldCode: Copy + Sized + 'static {
+    /// Key of the field in the VIN layout
+    const KEY: &'static str;
+    /// Every code, in the order of the decode table
+    const ALL: &'static [Self];
+
+    /// Code as it appears in the VIN
+    fn to_code(self) -> &'static str;
+
+    /// Decoded label, as in the decode table
+    fn display(self) -> &'static str;
+
+    /// Value for a VIN code, `None` for unknown codes
+    fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|c| c.to_code() == code)
+    }
+}
+
+/// Declare a field enum with its (variant, code, label) table
+macro_rules! field_code {
+    ($(#[$doc:meta])* $name:ident, $key:literal, [$(($variant:ident, $code:literal, $label:literal)),+ $(,)?]) => {
+        $(#[$doc])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($variant),+
+        }
+
+        impl FieldCode for $name {
+            const KEY: &'static str = $key;
+            const ALL: &'static [Self] = &[$($name::$variant),+];
+
+            fn to_code(self) -> &'static str {
+                match self {
+                    $($name::$variant => $code),+
+                }
+            }
+
+            fn display(self) -> &'static str {
+                match self {
+                    $($name::$variant => $label),+
+                }
+            }
+        }
+    };
+}
+
+field_code!(
+    /// Trim level
+    Version,
+    "Version",
+    [
+        (L, "D", "L"),
+        (Lx, "E", "LX"),
+        (Slx, "G", "SLX"),
+        (Gt, "P", "GT"),
+    ]
+);
+
+field_code!(
+    /// Driven wheels
+    Drive,
+    "Drive",
+    [(Rwd, "1", "RWD")]
+);
+
+impl Drive {
+    /// How the drivetrain is laid out, for the results summary
+    pub fn layout(self) -> &'static str {
+        match self {
+            Drive::Rwd => "rear-wheel drive through a live rear axle",
+        }
+    }
+}
+
+field_code!(
+    /// Engine option
+    Engine,
+    "Engine",
+    [
+        (Standard, "NA", "Standard 2.0"),
+        (HighPerformance, "NE", "High Performance 2.0"),
+    ]
+);
+
+field_code!(
+    /// Factory transmission
+    Gearbox,
+    "Gearbox",
+    [
+        (Automatic3Speed, "7", "3-spd Automatic"),
+        (Manual4Speed, "B", "4-spd Manual"),
+    ]
+);
+
+field_code!(
+    /// Model year
+    Year,
+    "Year",
+    [
+        (Y1971, "L", "1971"),
+        (Y1972, "M", "1972"),
+        (Y1973, "N", "1973"),
+        (Y1974, "P", "1974 (Facelift)"),
+        (Y1975, "R", "1975"),
+        (Y1976, "S", "1976"),
+    ]
+);
@@ -0,0 +1,128 @@
+//! Community-editable reference data (`vin_tables.toml`)
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+
+use crate::VEHICLE_MODELS;
+
+/// Config file name, looked up next to the executable and in the working directory
+pub const CONFIG_FILE: &str = "vin_tables.toml";
+
+/// Bundled copy used when no config file is found
+pub const DEFAULT_CONFIG: &str = include_str!("../vin_tables.toml");
+
+/// Contents of `vin_tables.toml`
+#[derive(Debug, Default, Deserialize)]
+pub struct VinConfig {
+    /// Field key -> longer help text
+    #[serde(default)]
+    pub descriptions: HashMap<String, String>,
+    /// Field key -> (code -> label), added to the built-in decode tables
+    #[serde(default)]
+    pub codes: HashMap<String, HashMap<String, String>>,
+    /// AssemblyPlant code -> country the plant is in
+    #[serde(default)]
+    pub plant_locations: HashMap<String, String>,
+    /// Authoring problems found while loading
+    #[serde(skip)]
+    pub warnings: Vec<String>,
+    /// Why a config file on disk was ignored in favour of the bundled copy
+    #[serde(skip)]
+    pub load_error: Option<String>,
+}
+
+/// Check the code tables against the known VIN layouts: unknown fields and
+/// codes whose length doesn't match their field can never be decoded
+pub fn validate(config: &VinConfig) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let mut keys: Vec<&String> = config.codes.keys().collect();
+    keys.sort();
+    for key in keys {
+        let fields: Vec<_> = VEHICLE_MODELS
+            .iter()
+            .filter_map(|m| m.structure.iter().find(|f| f.key == key.as_str()))
+            .collect();
+        if fields.is_empty() {
+            warnings.push(format!("[codes.{}]: no such field", key));
+            continue;
+        }
+        let mut codes: Vec<&String> = config.codes[key].keys().collect();
+        codes.sort();
+        for code in codes {
+            let len = code.chars().count();
+            if let Some(field) = fields.iter().find(|f| f.len != len) {
+                warnings.push(format!(
+                    "[codes.{}]: code \"{}\" has {} characters but the field holds {}",
+                    key, code, len, field.len
+                ));
+            }
+        }
+    }
+    warnings
+}
+
+static CONFIG: OnceLock<VinConfig> = OnceLock::new();
+
+/// Loaded config, read on first use
+pub fn get() -> &'static VinConfig {
+    CONFIG.get_or_init(load)
+}
+
+/// Places to look for the config file, in order
+fn candidate_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Some(dir) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(PathBuf::from))
+    {
+        paths.push(dir.join(CONFIG_FILE));
+    }
+    paths.push(PathBuf::from(CONFIG_FILE));
+    paths
+}
+
+/// Read the first config file found, falling back to the bundled copy
+fn load() -> VinConfig {
+    let mut load_error = None;
+    for path in candidate_paths() {
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                let error = format!("Ignoring {}: {}", path.display(), e);
+                eprintln!("{}", error);
+                load_error.get_or_insert(error);
+                continue;
+            }
+        };
+        match toml::from_str(&text) {
+            Ok(config) => return checked(config),
+            Err(e) => {
+                let error = format!("Ignoring {}: {}", path.display(), e);
+                eprintln!("{}", error);
+                load_error.get_or_insert(error);
+            }
+        }
+    }
+    let mut config = toml::from_str(DEFAULT_CONFIG).map_or_else(
+        |e| {
+            eprintln!("Bundled {} is invalid: {}", CONFIG_FILE, e);
+            VinConfig::default()
+        },
+        checked,
+    );
+    config.load_error = load_error;
+    config
+}
+
+/// Attach and print the validation warnings
+fn checked(mut config: VinConfig) -> VinConfig {
+    config.warnings = validate(&config);
+    for warning in &config.warnings {
+        eprintln!("Warning: {}: {}", CONFIG_FILE, warning);
+    }
+    config
+}
@@ -0,0 +1,457 @@
+//! Decode results: one status per VIN field, independent of the GUI
+
+use serde::Serialize;
+
+use crate::codes::FieldCode;
+use crate::{decode_confidence, DecodeMap, VinField};
+
+/// Status marker for a code that isn't in the table but uses the field's alphabet
+const STATUS_UNDOCUMENTED: &str = "!! [UNDOCUMENTED] !!";
+
+/// Status marker for a code with characters the field never uses
+const STATUS_INVALID: &str = "!! [INVALID] !!";
+
+/// How a field's raw code decoded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldStatus {
+    /// Known code with its label
+    Decoded(&'static str),
+    /// "-" placeholder without a table entry
+    Standard,
+    /// Not in the table, but uses the field's alphabet
+    Undocumented,
+    /// Characters the field never uses
+    Invalid,
+    /// Not decoded (the Serial) or empty
+    Raw,
+}
+
+impl FieldStatus {
+    /// Look up `val` in the field's decode table, exactly as given; pass
+    /// typed codes through [`normalize_code`](crate::normalize_code) first
+    pub fn of(decode_map: &DecodeMap, field: &VinField, val: &str) -> Self {
+        match decode_map.get(field.key).and_then(|m| m.get(val)) {
+            Some(d) => FieldStatus::Decoded(d),
+            None if val == "-" => FieldStatus::Standard,
+            None if field.key != "Serial" && !val.is_empty() => {
+                if plausible_code(decode_map, field, val) {
+                    FieldStatus::Undocumented
+                } else {
+                    FieldStatus::Invalid
+                }
+            }
+            _ => FieldStatus::Raw,
+        }
+    }
+
+    /// Short machine-readable name, used by the JSON export
+    pub fn kind(self) -> &'static str {
+        match self {
+            FieldStatus::Decoded(_) => "decoded",
+            FieldStatus::Standard => "standard",
+            FieldStatus::Undocumented => "undocumented",
+            FieldStatus::Invalid => "invalid",
+            FieldStatus::Raw => "raw",
+        }
+    }
+
+    /// Text shown in the Decoded column
+    pub fn label(self) -> &'static str {
+        match self {
+            FieldStatus::Decoded(label) => label,
+            FieldStatus::Standard => "Standard / None",
+            FieldStatus::Undocumented => STATUS_UNDOCUMENTED,
+            FieldStatus::Invalid => STATUS_INVALID,
+            FieldStatus::Raw => "",
+        }
+    }
+}
+
+/// Whether an unmapped code looks like a real one: every character is an
+/// uppercase letter or digit, and of a kind the field's known codes use
+fn plausible_code(decode_map: &DecodeMap, field: &VinField, val: &str) -> bool {
+    let codes = decode_map.get(field.key);
+    let uses = |kind: fn(&char) -> bool| {
+        codes.is_some_and(|m| m.keys().any(|code| code.chars().any(|c| kind(&c))))
+    };
+    let letters = uses(char::is_ascii_uppercase);
+    let digits = uses(char::is_ascii_digit);
+    val.chars()
+        .all(|c| (letters && c.is_ascii_uppercase()) || (digits && c.is_ascii_digit()))
+}
+
+/// Characters easily mistaken for each other when copying a VIN by hand
+const LOOK_ALIKES: &[(char, char)] = &[
+    ('O', '0'),
+    ('Q', '0'),
+    ('D', '0'),
+    ('I', '1'),
+    ('L', '1'),
+    ('Z', '2'),
+    ('S', '5'),
+    ('G', '6'),
+    ('B', '8'),
+];
+
+/// Cost of reading `b` where `a` was written: 0 when equal, 1 for a
+/// look-alike pair, 2 otherwise
+fn char_distance(a: char, b: char) -> u32 {
+    if a == b {
+        0
+    } else if LOOK_ALIKES.contains(&(a, b)) || LOOK_ALIKES.contains(&(b, a)) {
+        1
+    } else {
+        2
+    }
+}
+
+/// The known code (and its label) an unmapped `val` was most likely meant to
+/// be: the single closest code of the same length, at most one wrong
+/// character away, or only a look-alike for one-character fields (where any
+/// other character is as far from every code). `None` when several codes are
+/// equally close.
+pub fn suggest_code(
+    decode_map: &DecodeMap,
+    field: &VinField,
+    val: &str,
+) -> Option<(&'static str, &'static str)> {
+    let mut best = None;
+    let mut best_distance = u32::MAX;
+    let mut tied = false;
+    for (code, label) in decode_map.get(field.key)? {
+        if code.chars().count() != val.chars().count() {
+            continue;
+        }
+        let distance: u32 = code
+            .chars()
+            .zip(val.chars())
+            .map(|(a, b)| char_distance(a, b))
+            .sum();
+        if distance < best_distance {
+            best = Some((*code, *label));
+            best_distance = distance;
+            tied = false;
+        } else if distance == best_distance {
+            tied = true;
+        }
+    }
+    let max_distance = if field.len == 1 { 1 } else { 2 };
+    best.filter(|_| best_distance <= max_distance && !tied)
+}
+
+/// Decoded label for a field value, or a status marker when it has none
+pub fn field_status(decode_map: &DecodeMap, field: &VinField, val: &str) -> &'static str {
+    FieldStatus::of(decode_map, field, val).label()
+}
+
+/// Short ID of a complete VIN for comparing without posting it: the 32-bit
+/// FNV-1a hash of its bytes as "XXXX-XXXX" hex. Not a game checksum.
+pub fn share_code(vin: &str) -> String {
+    const FNV_OFFSET: u32 = 0x811C_9DC5;
+    const FNV_PRIME: u32 = 0x0100_0193;
+    let hash = vin
+        .bytes()
+        .fold(FNV_OFFSET, |h, b| (h ^ b as u32).wrapping_mul(FNV_PRIME));
+    format!("{:04X}-{:04X}", hash >> 16, hash & 0xFFFF)
+}
+
+/// Where a decoded VIN came from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DecodeSource {
+    /// Loaded from a carparts.txt
+    File,
+    /// Typed or pasted VIN
+    Manual,
+}
+
+/// A VIN's field values decoded against one model's tables
+#[derive(Debug)]
+pub struct DecodedVin<'a> {
+    structure: &'a [VinField],
+    /// Raw codes, in `structure` order
+    values: Vec<String>,
+    statuses: Vec<FieldStatus>,
+    /// Likely intended `(code, label)` of unmapped codes, see [`suggest_code`]
+    suggestions: Vec<Option<(&'static str, &'static str)>>,
+    /// Free-text note carried into exports, e.g. "project car, needs paint"
+    notes: Option<String>,
+    source: Option<DecodeSource>,
+}
+
+impl<'a> DecodedVin<'a> {
+    /// Start building a VIN field by field for `structure`
+    pub fn builder(structure: &'a [VinField], decode_map: &'a DecodeMap) -> DecodedVinBuilder<'a> {
+        DecodedVinBuilder {
+            structure,
+            decode_map,
+            strict: true,
+            values: vec![None; structure.len()],
+            errors: Vec::new(),
+        }
+    }
+
+    /// Decode the values returned by `get_value` for every field of `structure`.
+    /// Values are looked up as given, see [`FieldStatus::of`].
+    pub fn new<'v>(
+        structure: &'a [VinField],
+        decode_map: &DecodeMap,
+        get_value: impl Fn(&str) -> &'v str,
+    ) -> Self {
+        let values: Vec<String> = structure
+            .iter()
+            .map(|f| get_value(f.key).to_string())
+            .collect();
+        let statuses: Vec<FieldStatus> = structure
+            .iter()
+            .zip(&values)
+            .map(|(f, val)| FieldStatus::of(decode_map, f, val))
+            .collect();
+        let suggestions = structure
+            .iter()
+            .zip(&values)
+            .zip(&statuses)
+            .map(|((f, val), status)| match status {
+                FieldStatus::Undocumented | FieldStatus::Invalid => {
+                    suggest_code(decode_map, f, val)
+                }
+                _ => None,
+            })
+            .collect();
+        DecodedVin {
+            structure,
+            values,
+            statuses,
+            suggestions,
+            notes: None,
+            source: None,
+        }
+    }
+
+    /// Split `vin` into the fields of `structure` and decode them; fields past
+    /// the end of a short VIN are left empty
+    pub fn from_vin(structure: &'a [VinField], decode_map: &DecodeMap, vin: &str) -> Self {
+        let mut pos = 0;
+        let values: Vec<&str> = structure
+            .iter()
+            .map(|field| {
+                let val = vin.get(pos..pos + field.len).unwrap_or("");
+                pos += field.len;
+                val
+            })
+            .collect();
+        DecodedVin::new(structure, decode_map, |key| {
+            structure
+                .iter()
+                .position(|f| f.key == key)
+                .map_or("", |i| values[i])
+        })
+    }
+
+    /// Attach a note for exports; blank notes are dropped
+    pub fn with_notes(mut self, notes: &str) -> Self {
+        let notes = notes.trim();
+        self.notes = (!notes.is_empty()).then(|| notes.to_string());
+        self
+    }
+
+    /// Attached note, if any
+    pub fn notes(&self) -> Option<&str> {
+        self.notes.as_deref()
+    }
+
+    /// Record where the VIN came from, for exports
+    pub fn with_source(mut self, source: DecodeSource) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Where the VIN came from, `None` when not recorded
+    pub fn source(&self) -> Option<DecodeSource> {
+        self.source
+    }
+
+    /// `(field key, display name, raw code, status)` for every field in VIN order
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &'static str, &str, FieldStatus)> {
+        self.structure
+            .iter()
+            .zip(&self.values)
+            .zip(&self.statuses)
+            .map(|((f, val), status)| (f.key, f.display, val.as_str(), *status))
+    }
+
+    /// `(field, raw code, status)` for every field in VIN order
+    pub fn fields(&self) -> impl Iterator<Item = (&'a VinField, &str, FieldStatus)> {
+        self.structure
+            .iter()
+            .zip(&self.values)
+            .zip(&self.statuses)
+            .map(|((f, val), status)| (f, val.as_str(), *status))
+    }
+
+    /// Layout the VIN was decoded with
+    pub fn structure(&self) -> &'a [VinField] {
+        self.structure
+    }
+
+    /// Status of a field, `Raw` when the layout has no such field
+    pub fn status(&self, key: &str) -> FieldStatus {
+        self.structure
+            .iter()
+            .position(|f| f.key == key)
+            .map_or(FieldStatus::Raw, |i| self.statuses[i])
+    }
+
+    /// Likely intended `(code, label)` when the field's code is unmapped
+    pub fn suggestion(&self, key: &str) -> Option<(&'static str, &'static str)> {
+        self.structure
+            .iter()
+            .position(|f| f.key == key)
+            .and_then(|i| self.suggestions[i])
+    }
+
+    /// Raw code of a field, empty when the layout has no such field
+    pub fn value(&self, key: &str) -> &str {
+        self.structure
+            .iter()
+            .position(|f| f.key == key)
+            .map_or("", |i| &self.values[i])
+    }
+
+    /// Typed code of a field with a closed set of codes, `None` when unknown
+    pub fn get<T: FieldCode>(&self) -> Option<T> {
+        T::from_code(self.value(T::KEY))
+    }
+
+    /// All raw codes joined back into the VIN
+    pub fn complete_vin(&self) -> String {
+        self.values.concat()
+    }
+}
+
+/// One entry of the JSON `fields` array
+#[derive(Serialize)]
+struct FieldJson<'v> {
+    key: &'static str,
+    name: &'static str,
+    code: &'v str,
+    status: &'static str,
+    /// `None` (null) for raw, undocumented and invalid codes
+    decoded: Option<&'static str>,
+}
+
+/// The JSON export layout of a [`DecodedVin`], see `export::JSON_SCHEMA`
+#[derive(Serialize)]
+pub struct DecodedVinJson<'v> {
+    complete_vin: String,
+    share_code: String,
+    confidence: u8,
+    notes: Option<&'v str>,
+    source: Option<DecodeSource>,
+    fields: Vec<FieldJson<'v>>,
+}
+
+impl<'v> From<&'v DecodedVin<'_>> for DecodedVinJson<'v> {
+    fn from(decoded: &'v DecodedVin<'_>) -> Self {
+        let complete_vin = decoded.complete_vin();
+        let fields = decoded
+            .iter()
+            .map(|(key, name, code, status)| FieldJson {
+                key,
+                name,
+                code,
+                status: status.kind(),
+                decoded: matches!(status, FieldStatus::Decoded(_) | FieldStatus::Standard)
+                    .then(|| status.label()),
+            })
+            .collect();
+        DecodedVinJson {
+            share_code: share_code(&complete_vin),
+            complete_vin,
+            confidence: decode_confidence(decoded),
+            notes: decoded.notes(),
+            source: decoded.source,
+            fields,
+        }
+    }
+}
+
+/// Field-by-field VIN construction, see [`DecodedVin::builder`]. Errors are
+/// collected while setting and reported by the `build` methods.
+pub struct DecodedVinBuilder<'a> {
+    structure: &'a [VinField],
+    decode_map: &'a DecodeMap,
+    /// Reject codes that aren't in the field's table
+    strict: bool,
+    /// Codes set so far, in `structure` order
+    values: Vec<Option<String>>,
+    errors: Vec<String>,
+}
+
+impl<'a> DecodedVinBuilder<'a> {
+    /// Whether codes must be in the field's table (default); the "-"
+    /// placeholder and fields without a table are always accepted
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Set the code of the field with key `key`
+    pub fn set(mut self, key: &str, code: &str) -> Self {
+        let Some(i) = self.structure.iter().position(|f| f.key == key) else {
+            self.errors.push(format!("Unknown field {}", key));
+            return self;
+        };
+        let field = &self.structure[i];
+        if code.chars().count() != field.len {
+            self.errors.push(format!(
+                "{} needs exactly {} character(s), got \"{}\"",
+                field.display, field.len, code
+            ));
+        } else if self.strict
+            && matches!(
+                FieldStatus::of(self.decode_map, field, code),
+                FieldStatus::Undocumented | FieldStatus::Invalid
+            )
+        {
+            self.errors
+                .push(format!("{} has no code \"{}\"", field.display, code));
+        } else {
+            self.values[i] = Some(code.to_string());
+        }
+        self
+    }
+
+    /// The assembled VIN string, or every error in setting order followed by
+    /// the fields never set
+    pub fn build_vin(self) -> Result<String, Vec<String>> {
+        self.codes().map(|codes| codes.concat())
+    }
+
+    /// The assembled VIN decoded against the builder's tables
+    pub fn build(self) -> Result<DecodedVin<'a>, Vec<String>> {
+        let (structure, decode_map) = (self.structure, self.decode_map);
+        let codes = self.codes()?;
+        Ok(DecodedVin::new(structure, decode_map, |key| {
+            structure
+                .iter()
+                .position(|f| f.key == key)
+                .map_or("", |i| codes[i].as_str())
+        }))
+    }
+
+    /// Every field's code in VIN order, or the collected errors
+    fn codes(self) -> Result<Vec<String>, Vec<String>> {
+        let mut errors = self.errors;
+        for (field, value) in self.structure.iter().zip(&self.values) {
+            if value.is_none() {
+                errors.push(format!("{} is not set", field.display));
+            }
+        }
+        if errors.is_empty() {
+            Ok(self.values.into_iter().flatten().collect())
+        } else {
+            Err(errors)
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! Export of decoded VINs to files
+
+use std::path::{Path, PathBuf};
+
+use crate::decoded::{share_code, DecodedVinJson};
+use crate::{
+    all_field_options, decode_confidence, field_positions, DecodedVin, REDACTED_SERIAL,
+    VEHICLE_MODELS,
+};
+
+/// Wrap a value getter so the Serial reads as [`REDACTED_SERIAL`] when `redact` is set
+pub fn redact_serial<'a>(
+    get_value: impl Fn(&str) -> &'a str,
+    redact: bool,
+) -> impl Fn(&str) -> &'a str {
+    move |key| {
+        let val = get_value(key);
+        if redact && key == "Serial" && !val.is_empty() {
+            REDACTED_SERIAL
+        } else {
+            val
+        }
+    }
+}
+
+/// Supported export formats
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Plain-text spec sheet
+    Text,
+    /// One object with every field, schema in [`JSON_SCHEMA`]
+    Json,
+    /// One row per field
+    Csv,
+    /// MediaWiki table for the community wiki
+    Wiki,
+    /// `Field=Decoded` lines for shell tools
+    KeyValue,
+}
+
+impl ExportFormat {
+    /// All formats, in the order their buttons are shown
+    pub const ALL: [ExportFormat; 5] = [
+        ExportFormat::Text,
+        ExportFormat::Json,
+        ExportFormat::Csv,
+        ExportFormat::Wiki,
+        ExportFormat::KeyValue,
+    ];
+
+    /// Human-readable name for buttons and file dialogs
+    pub fn name(self) -> &'static str {
+        match self {
+            ExportFormat::Text => "Text",
+            ExportFormat::Json => "JSON",
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Wiki => "Wiki",
+            ExportFormat::KeyValue => "key=value",
+        }
+    }
+
+    /// File extension without the dot
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Text => "txt",
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Wiki => "wiki",
+            ExportFormat::KeyValue => "env",
+        }
+    }
+
+    /// Render the decoded VIN in this format
+    pub fn render(self, decoded: &DecodedVin) -> String {
+        match self {
+            ExportFormat::Text => render_text(decoded),
+            ExportFormat::Json => render_json(decoded),
+            ExportFormat::Csv => render_csv(decoded),
+            ExportFormat::Wiki => render_wiki(decoded),
+            ExportFormat::KeyValue => render_key_value(decoded),
+        }
+    }
+}
+
+/// Plain-text spec sheet with aligned columns
+fn render_text(decoded: &DecodedVin) -> String {
+    let mut out = String::from("My Winter Car VIN Decoder\n\n");
+    out.push_str(&format!("{:<18} {:<7} {}\n", "Field", "Value", "Decoded"));
+    for (_, display, val, status) in decoded.iter() {
+        out.push_str(format!("{:<18} {:<7} {}", display, val, status.label()).trim_end());
+        out.push('\n');
+    }
+    let complete_vin = decoded.complete_vin();
+    out.push_str(&format!("\nComplete VIN: {}\n", complete_vin));
+    out.push_str(&format!("Share code:   {}\n", share_code(&complete_vin)));
+    out.push_str(&format!("Confidence:   {}%\n", decode_confidence(decoded)));
+    if let Some(notes) = decoded.notes() {
+        out.push_str(&format!("\nNotes:\n{}\n", notes));
+    }
+    out
+}
+
+/// Layout of the JSON export, printed by `--format json --help`
+pub const JSON_SCHEMA: &str = r#"{
+  "complete_vin": string, complete VIN
+  "share_code": string,   "XXXX-XXXX" short ID of the VIN
+  "confidence": number,   0-100 decode confidence, as shown under the results
+  "notes": string|null,   free-text note attached to the decode
+  "source": string|null,  "file" (carparts.txt) or "manual" (typed VIN)
+  "fields": [             every field in VIN order
+    {
+      "key": string,      field key, e.g. "AssemblyPlant"
+      "name": string,     display name, e.g. "Assembly Plant"
+      "code": string,     raw code
+      "status": string,   "decoded", "standard", "undocumented", "invalid" or "raw"
+      "decoded": string|null  decoded label, null for raw, undocumented
+                          and invalid codes
+    }
+  ]
+}"#;
+
+/// JSON object as described by [`JSON_SCHEMA`]
+fn render_json(decoded: &DecodedVin) -> String {
+    // Only strings, numbers and nulls: serializing can't fail
+    let mut json = serde_json::to_string_pretty(&DecodedVinJson::from(decoded)).unwrap_or_default();
+    json.push('\n');
+    json
+}
+
+/// `s` as a CSV cell, quoted when it holds a separator, quote or line break
+fn csv_cell(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// CSV with a header row and one row per field in VIN order, the same
+/// Field, Value and Decoded columns as the results table
+fn render_csv(decoded: &DecodedVin) -> String {
+    let mut out = String::from("Field,Value,Decoded\n");
+    for (_, display, val, status) in decoded.iter() {
+        let row = [display, val, status.label()].map(csv_cell).join(",");
+        out.push_str(&row);
+        out.push('\n');
+    }
+    out
+}
+
+/// `s` as literal text in wiki markup: characters that start links,
+/// templates, cell separators, quotes (bold/italic) or tags become entities
+fn wiki_text(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '|' | '!' | '[' | ']' | '{' | '}' | '\'' | '~' => {
+                out.push_str(&format!("&#{};", c as u32))
+            }
+            '\n' | '\r' => out.push(' '),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// MediaWiki `wikitable` with the complete VIN as caption and one row per field
+fn render_wiki(decoded: &DecodedVin) -> String {
+    let complete_vin = decoded.complete_vin();
+    let mut out = String::from("{| class=\"wikitable\"\n");
+    out.push_str(&format!(
+        "|+ VIN {} (share code {})\n",
+        wiki_text(&complete_vin),
+        share_code(&complete_vin)
+    ));
+    out.push_str("! Field !! Code !! Decoded\n");
+    for (_, display, val, status) in decoded.iter() {
+        out.push_str("|-\n");
+        let row = [display, val, status.label()].map(wiki_text).join(" || ");
+        out.push_str(format!("| {}", row).trim_end());
+        out.push('\n');
+    }
+    out.push_str("|}\n");
+    out
+}
+
+/// `VIN=...` followed by one `Key=Decoded` line per field in VIN order;
+/// fields without a label (the Serial) give their raw code
+fn render_key_value(decoded: &DecodedVin) -> String {
+    let mut out = format!("VIN={}\n", decoded.complete_vin());
+    for (key, _, val, status) in decoded.iter() {
+        let label = status.label();
+        let value = if label.is_empty() { val } else { label };
+        out.push_str(&format!("{}={}\n", key, value));
+    }
+    out
+}
+
+/// Fillable Markdown spec sheet of every model: each field with its position,
+/// an empty Code column and the known options
+pub fn render_template() -> String {
+    let mut out = String::from("# My Winter Car VIN template\n");
+    for model in VEHICLE_MODELS {
+        let map = model.tables();
+        out.push_str(&format!("\n## {} (Model {})\n\n", model.name, model.code));
+        out.push_str("| Pos | Field | Code | Options |\n");
+        out.push_str("|-----|-------|------|---------|\n");
+        let options = all_field_options(model.structure, &map);
+        let positions = field_positions(model.structure);
+        for ((field, (_, codes)), pos) in model.structure.iter().zip(options).zip(positions) {
+            let choices = if codes.is_empty() {
+                format!("free-form, {} characters", field.len)
+            } else {
+                codes
+                    .iter()
+                    .map(|(code, label)| format!("{} = {}", code, label))
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            };
+            out.push_str(&format!(
+                "| {} | {} |  | {} |\n",
+                pos,
+                field.display,
+                choices.replace('|', "\\|")
+            ));
+        }
+    }
+    out
+}
+
+/// Write every format next to `base` with its own extension, returns the written paths
+pub fn write_bundle(base: &Path, decoded: &DecodedVin) -> Result<Vec<PathBuf>, String> {
+    ExportFormat::ALL
+        .iter()
+        .map(|format| {
+            let path = base.with_extension(format.extension());
+            std::fs::write(&path, format.render(decoded))
+                .map(|_| path.clone())
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+        })
+        .collect()
+}
@@ -0,0 +1,1595 @@
+//! Reading carparts.txt saves and decoding My Winter Car VINs, without the GUI
+//!
+//! The binary format parser ([`parse_vingen4_file`], [`read_header`],
+//! [`parse_value`], [`parse_dictionary_vec`]), the save loader
+//! ([`read_carparts`], plain or gzip), typed VIN input ([`parse_vin_input`],
+//! [`deep_link`]), the decode tables ([`VEHICLE_MODELS`], [`decode_vin`]) and
+//! the [`export`] formats live here; the `mwc-vin-decoder` binary is the egui
+//! front-end on top.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::ops::Range;
+use std::path::Path;
+
+use byteorder::LittleEndian;
+use byteorder::ReadBytesExt;
+
+pub mod codes;
+pub mod config;
+pub mod decoded;
+pub mod export;
+
+#[cfg(test)]
+mod tests;
+#[cfg(test)]
+mod writer;
+
+use decoded::{DecodedVin, FieldStatus};
+
+/// Empty string constant
+pub const EMPTY: &str = "";
+/// Entry start byte in binary format
+pub const HX_START_ENTRY: u8 = 0x7E;
+
+/// Marker byte after a container type, or first byte of a single value
+pub const HX_NO_CONTAINER: u8 = 0xFF;
+
+// VINGen4 binary format type identifiers
+pub const CONTAINER_TYPE_NONE: u8 = 0x00;
+pub const CONTAINER_TYPE_DICTIONARY: u8 = 0x52;
+pub const CONTAINER_TYPE_LIST: u8 = 0x53;
+pub const VALUE_TYPE_STRING: u32 = 0xFDE9F1EE;
+pub const VALUE_TYPE_INT32: u32 = 0xE2A80856;
+pub const VALUE_TYPE_BOOL: u32 = 0xAD4D7C9C;
+
+/// VIN field definition (key, display name, length)
+#[derive(Debug)]
+pub struct VinField {
+    /// Field key for lookup
+    pub key: &'static str,
+    /// Human-readable name
+    pub display: &'static str,
+    /// Field length in VIN
+    pub len: usize,
+}
+
+impl VinField {
+    /// Longer help text from the community config, if any
+    pub fn description(&self) -> Option<&'static str> {
+        config::get().descriptions.get(self.key).map(String::as_str)
+    }
+}
+
+/// Field key -> (code -> label) decode tables
+pub type DecodeMap = HashMap<&'static str, HashMap<&'static str, &'static str>>;
+
+/// Ordered VIN field structure
+pub const VIN_STRUCTURE: &[VinField] = &[
+    VinField {
+        key: "Country",
+        display: "Country",
+        len: 1,
+    },
+    VinField {
+        key: "AssemblyPlant",
+        display: "Assembly Plant",
+        len: 1,
+    },
+    VinField {
+        key: "Model",
+        display: "Model",
+        len: 1,
+    },
+    VinField {
+        key: "Body",
+        display: "Body",
+        len: 1,
+    },
+    VinField {
+        key: "Version",
+        display: "Version",
+        len: 1,
+    },
+    VinField {
+        key: "Year",
+        display: "Year",
+        len: 1,
+    },
+    VinField {
+        key: "Month",
+        display: "Month",
+        len: 1,
+    },
+    VinField {
+        key: "Serial",
+        display: "Serial",
+        len: 5,
+    },
+    VinField {
+        key: "Drive",
+        display: "Drive",
+        len: 1,
+    },
+    VinField {
+        key: "Engine",
+        display: "Engine",
+        len: 2,
+    },
+    VinField {
+        key: "Gearbox",
+        display: "Gearbox",
+        len: 1,
+    },
+    VinField {
+        key: "AxleRatio",
+        display: "Axle Ratio",
+        len: 1,
+    },
+    VinField {
+        key: "AxleLock",
+        display: "Axle Lock",
+        len: 1,
+    },
+    VinField {
+        key: "ColorsBody",
+        display: "Body Colour",
+        len: 1,
+    },
+    VinField {
+        key: "VinylRoof",
+        display: "Vinyl Roof",
+        len: 1,
+    },
+    VinField {
+        key: "InteriorTrim",
+        display: "Interior Trim",
+        len: 1,
+    },
+    VinField {
+        key: "Radio",
+        display: "Radio",
+        len: 1,
+    },
+    VinField {
+        key: "InstrumentPanel",
+        display: "Instrument Panel",
+        len: 1,
+    },
+    VinField {
+        key: "Windshield",
+        display: "Windshield",
+        len: 1,
+    },
+    VinField {
+        key: "Seats",
+        display: "Seats",
+        len: 1,
+    },
+    VinField {
+        key: "Suspension",
+        display: "Suspension",
+        len: 1,
+    },
+    VinField {
+        key: "PowerBrakes",
+        display: "Brakes",
+        len: 1,
+    },
+    VinField {
+        key: "Wheels",
+        display: "Wheels",
+        len: 1,
+    },
+    VinField {
+        key: "WindowHeater",
+        display: "Rear Window",
+        len: 1,
+    },
+];
+
+/// Parse VINGen4 header, returns (container_type, key_type, value_type, offset)
+///
+/// Container bodies start with their type followed by a 0xFF marker. A body
+/// that starts with the 0xFF marker itself has no container and holds a single
+/// value right after its value type; it is reported as `CONTAINER_TYPE_NONE`.
+pub fn read_header(body: &[u8]) -> Option<(u8, u32, u32, usize)> {
+    let first = *body.first()?;
+    if first == HX_NO_CONTAINER {
+        let value_type = body.get(1..5)?.read_u32::<LittleEndian>().ok()?;
+        return Some((CONTAINER_TYPE_NONE, 0, value_type, 5));
+    }
+    let container_type = first;
+    let mut offset = 2;
+    let mut key_type = 0u32;
+    if container_type == CONTAINER_TYPE_DICTIONARY {
+        key_type = body
+            .get(offset..offset + 4)?
+            .read_u32::<LittleEndian>()
+            .ok()?;
+        offset += 4;
+    }
+    let value_type = body
+        .get(offset..offset + 4)?
+        .read_u32::<LittleEndian>()
+        .ok()?;
+    offset += 4;
+    let prop_size = if key_type == 0 { 1 } else { 2 };
+    offset += prop_size;
+    Some((container_type, key_type, value_type, offset))
+}
+
+/// Read a string length prefix, returns (length, prefix size in bytes)
+///
+/// Lengths are 7-bit encoded like .NET's `BinaryWriter`: the high bit of each
+/// byte flags a continuation, so lengths below 128 stay a single byte.
+pub fn read_string_len(data: &[u8], offset: usize) -> Option<(usize, usize)> {
+    let mut len = 0usize;
+    for (i, &b) in data.get(offset..)?.iter().take(5).enumerate() {
+        len |= ((b & 0x7F) as usize) << (7 * i);
+        if b & 0x80 == 0 {
+            return Some((len, i + 1));
+        }
+    }
+    None
+}
+
+/// Parse value from binary (string, int32, bool, or hex)
+pub fn parse_value(data: &[u8], offset: &mut usize, value_type: u32) -> Option<String> {
+    match value_type {
+        VALUE_TYPE_STRING => {
+            let (strlen, prefix_len) = read_string_len(data, *offset)?;
+            let start = *offset + prefix_len;
+            let remaining = data.len() - start;
+            // Truncated save: the string runs past the end of the data
+            if strlen > remaining {
+                return None;
+            }
+            // Undecodable bytes (suspected length-encoding mismatch) read as ""
+            let s = std::str::from_utf8(&data[start..start + strlen])
+                .unwrap_or_default()
+                .to_string();
+            *offset = start + strlen;
+            Some(s)
+        }
+        VALUE_TYPE_INT32 => {
+            if *offset + 4 > data.len() {
+                return None;
+            }
+            let val = (&data[*offset..*offset + 4])
+                .read_i32::<LittleEndian>()
+                .ok()?;
+            *offset += 4;
+            Some(val.to_string())
+        }
+        VALUE_TYPE_BOOL => {
+            let b = data.get(*offset).copied()? != 0;
+            *offset += 1;
+            Some(if b { "true" } else { "false" }.to_string())
+        }
+        _ => {
+            if *offset + 4 > data.len() {
+                return None;
+            }
+            let hex = data[*offset..*offset + 4]
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>();
+            *offset += 4;
+            Some(hex)
+        }
+    }
+}
+
+/// Valid UTF-8 prefix of a string value at `offset` that runs past the end of
+/// `data`; `None` for other value types and strings that fit
+fn truncated_string(data: &[u8], offset: usize, value_type: u32) -> Option<String> {
+    if value_type != VALUE_TYPE_STRING {
+        return None;
+    }
+    let (strlen, prefix_len) = read_string_len(data, offset)?;
+    let bytes = data.get(offset + prefix_len..)?;
+    if strlen <= bytes.len() {
+        return None;
+    }
+    let valid = match std::str::from_utf8(bytes) {
+        Ok(s) => s,
+        Err(e) => std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or(""),
+    };
+    Some(valid.to_string())
+}
+
+/// Strip padding whitespace and NUL bytes left over from fixed-width fields
+pub fn clean_entry(s: &str) -> String {
+    s.trim_matches(|c: char| c.is_whitespace() || c == '\0')
+        .to_string()
+}
+
+/// Keys whose int32 values are counts or numbers and never negative,
+/// shown as unsigned (0xFFFFFFFF reads 4294967295 instead of -1)
+pub const UNSIGNED_INT32_KEYS: &[&str] = &["Serial"];
+
+/// Raw 32 bits of a parsed int32 value, whether it was shown signed or unsigned
+pub fn int32_bits(val: &str) -> Option<u32> {
+    val.parse::<i32>()
+        .map(|v| v as u32)
+        .or_else(|_| val.parse::<u32>())
+        .ok()
+}
+
+/// Parse binary dictionary into key-value pairs, noting truncated or
+/// unreadable values in `warnings`
+///
+/// A `key_type` of 0 means a keyless container (e.g. the 0x53 list sections); its
+/// values are returned keyed by their index.
+pub fn parse_dictionary_vec(
+    data: &[u8],
+    key_type: u32,
+    value_type: u32,
+    warnings: &mut Vec<String>,
+) -> Vec<(String, String)> {
+    if data.len() < 4 {
+        return vec![];
+    }
+    let count = (&data[0..4]).read_u32::<LittleEndian>().unwrap_or(0) as usize;
+    let mut offset = 4;
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        // Stop at the end of the data instead of emitting empty pairs
+        if offset >= data.len() {
+            warnings.push(format!(
+                "Dictionary ends after {} of {} entries",
+                entries.len(),
+                count
+            ));
+            break;
+        }
+        // Keyless containers (lists) carry no key bytes, so index the values instead
+        let key = if key_type == 0 {
+            entries.len().to_string()
+        } else {
+            let Some(key) = parse_value(data, &mut offset, key_type) else {
+                break;
+            };
+            key
+        };
+        let value_at = offset;
+        let Some(mut val) = parse_value(data, &mut offset, value_type) else {
+            // A truncated save cuts the final string short: keep its valid prefix
+            match truncated_string(data, value_at, value_type) {
+                Some(prefix) => {
+                    warnings.push(format!(
+                        "{}: value runs past the end of the data, kept {:?}",
+                        clean_entry(&key),
+                        prefix
+                    ));
+                    entries.push((clean_entry(&key), clean_entry(&prefix)));
+                }
+                None => warnings.push(format!("{}: no value", clean_entry(&key))),
+            }
+            break;
+        };
+        let key = clean_entry(&key);
+        // Only a non-empty string has a length prefix longer than one byte
+        if value_type == VALUE_TYPE_STRING && val.is_empty() && offset > value_at + 1 {
+            warnings.push(format!(
+                "{}: invalid UTF-8 in its {}-byte value (suspected length-encoding mismatch)",
+                key,
+                offset - value_at - 1
+            ));
+        }
+        if value_type == VALUE_TYPE_INT32 && UNSIGNED_INT32_KEYS.contains(&key.as_str()) {
+            if let Some(bits) = int32_bits(&val) {
+                val = bits.to_string();
+            }
+        }
+        entries.push((key, clean_entry(&val)));
+    }
+    entries
+}
+
+/// Decimal places shown for float values unless changed in the section inspector
+pub const DEFAULT_FLOAT_DECIMALS: usize = 2;
+
+/// A float value rounded for display, e.g. "3.439999" to "3.44" at 2 decimals;
+/// `None` when it isn't a finite number
+pub fn format_float(value: &str, decimals: usize) -> Option<String> {
+    let value = value.trim().parse::<f64>().ok().filter(|v| v.is_finite())?;
+    Some(format!("{:.*}", decimals, value))
+}
+
+/// A `float(...)` typed value with its number rounded, other values unchanged
+pub fn round_typed_float(raw: &str, decimals: usize) -> Cow<'_, str> {
+    match unwrap_typed_value(raw) {
+        (Some("float"), inner) => match format_float(inner, decimals) {
+            Some(rounded) => Cow::Owned(format!("float({})", rounded)),
+            None => Cow::Borrowed(raw),
+        },
+        _ => Cow::Borrowed(raw),
+    }
+}
+
+/// Split a game-typed value like `string(S)` or `int(0)` into (type name, inner value)
+///
+/// The binary dictionary declares one value type for all entries, so values are
+/// not individually tagged there. Mixed types show up one level higher instead:
+/// the game stores each value as a string wrapped in its own type name.
+/// Values without a wrapper are returned unchanged with no type.
+pub fn unwrap_typed_value(raw: &str) -> (Option<&str>, &str) {
+    const TYPES: &[&str] = &["string", "int", "float", "bool"];
+    for ty in TYPES {
+        if let Some(inner) = raw
+            .strip_prefix(ty)
+            .and_then(|rest| rest.strip_prefix('('))
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            let inner = inner.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+            return (Some(ty), inner);
+        }
+    }
+    (None, raw)
+}
+
+/// Information gathered while scanning a carparts.txt, for troubleshooting
+#[derive(Clone, Debug, Default)]
+pub struct ParseDiagnostics {
+    /// Tag of each entry in file order, with the byte range of its body
+    pub sections: Vec<(String, Range<usize>)>,
+    /// Tag of the decoded VIN section (its suffix is the format version)
+    pub vin_tag: Option<String>,
+    /// Key type ID of the VIN section, `None` for a single value
+    pub key_type: Option<u32>,
+    /// Value type ID of the VIN section
+    pub value_type: Option<u32>,
+    /// Time spent scanning the buffer
+    pub parse_time: Option<std::time::Duration>,
+    /// int32 values of the VIN section whose signed and unsigned readings differ
+    pub int32_values: Vec<(String, u32)>,
+    /// Raw 4-byte values of an unknown value type, for guessing the real type
+    pub unknown_values: Vec<(String, [u8; 4])>,
+    /// Entry starts whose tag or body ran past the end of the file
+    pub malformed_entries: usize,
+    /// Truncated or unreadable values in the VIN section
+    pub warnings: Vec<String>,
+    /// The scanned data when the caller hands it over (as [`parse_carparts`]
+    /// does), for reading single sections with [`ParseDiagnostics::section_body`]
+    pub buffer: Vec<u8>,
+}
+
+impl ParseDiagnostics {
+    /// Body of the `index`th entry of `sections`, `None` when the buffer wasn't kept
+    pub fn section_body(&self, index: usize) -> Option<&[u8]> {
+        let (_, range) = self.sections.get(index)?;
+        self.buffer.get(range.clone())
+    }
+
+    /// "Key type: string (0xFDE9F1EE)" style lines for the VIN section's type IDs
+    /// and the parse time
+    pub fn detail_lines(&self) -> Vec<String> {
+        let describe = |type_id: u32| match value_type_name(type_id) {
+            Some(name) => format!("{} (0x{:08X})", name, type_id),
+            None => format!("unknown: 0x{:08X}", type_id),
+        };
+        let mut lines = Vec::new();
+        if let Some(key_type) = self.key_type {
+            lines.push(format!("Key type: {}", describe(key_type)));
+        }
+        if let Some(value_type) = self.value_type {
+            lines.push(format!("Value type: {}", describe(value_type)));
+        }
+        for (key, bits) in &self.int32_values {
+            lines.push(format!(
+                "{}: {} signed / {} unsigned",
+                key, *bits as i32, bits
+            ));
+        }
+        for warning in &self.warnings {
+            lines.push(format!("Warning: {}", warning));
+        }
+        if self.malformed_entries > 0 {
+            lines.push(format!(
+                "Skipped {} malformed entries",
+                self.malformed_entries
+            ));
+        }
+        if let Some(parse_time) = self.parse_time {
+            lines.push(format!(
+                "Parse time: {:.2} ms",
+                parse_time.as_secs_f64() * 1000.0
+            ));
+        }
+        lines
+    }
+
+    /// Aligned block reading each unknown 4-byte value as u32, i32 and f32
+    pub fn number_reading_lines(&self) -> Vec<String> {
+        let width = self
+            .unknown_values
+            .iter()
+            .map(|(key, _)| key.len())
+            .max()
+            .unwrap_or(0)
+            .max("Key".len());
+        let mut lines = vec![format!(
+            "{:<width$}  {:<8}  {:>10}  {:>11}  {:>12}",
+            "Key", "Hex", "u32", "i32", "f32"
+        )];
+        for (key, bytes) in &self.unknown_values {
+            let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            lines.push(format!(
+                "{:<width$}  {:<8}  {:>10}  {:>11}  {:>12}",
+                key,
+                hex,
+                u32::from_le_bytes(*bytes),
+                i32::from_le_bytes(*bytes),
+                format!("{:e}", f32::from_le_bytes(*bytes)),
+            ));
+        }
+        lines
+    }
+}
+
+/// Friendly name of a supported key/value type ID
+pub fn value_type_name(type_id: u32) -> Option<&'static str> {
+    match type_id {
+        VALUE_TYPE_STRING => Some("string"),
+        VALUE_TYPE_INT32 => Some("int32"),
+        VALUE_TYPE_BOOL => Some("bool"),
+        _ => None,
+    }
+}
+
+/// Split a complete VIN into (key, value) entries in field order
+pub fn split_vin_entries(vin: &str) -> Vec<(String, String)> {
+    let model = model_index_for_code(model_code_in_vin(vin)).unwrap_or(0);
+    let structure = VEHICLE_MODELS[model].structure;
+    let mut fields = parse_vin(vin, structure);
+    structure
+        .iter()
+        .map(|f| (f.key.to_string(), fields.remove(f.key).unwrap_or_default()))
+        .collect()
+}
+
+/// Section names that identify a game save
+pub const KNOWN_SAVE_TAGS: &[&str] = &["VINGen4"];
+
+/// Cheap check that a buffer is a game save: an entry start marker followed
+/// by one of the [`KNOWN_SAVE_TAGS`] appears somewhere in it
+pub fn looks_like_carparts(buffer: &[u8]) -> bool {
+    KNOWN_SAVE_TAGS.iter().any(|tag| {
+        let mut marker = vec![HX_START_ENTRY, tag.len() as u8];
+        marker.extend_from_slice(tag.as_bytes());
+        buffer.windows(marker.len()).any(|w| w == marker)
+    })
+}
+
+/// VIN entries of every vehicle in a carparts.txt, in file order
+pub type Vehicles = Vec<Vec<(String, String)>>;
+/// Outcome of reading a carparts.txt
+pub type LoadResult = (Result<Vehicles, String>, ParseDiagnostics);
+
+/// Load error shown when the parser panics
+pub const PARSE_PANIC_ERROR: &str = "Parsing failed unexpectedly — please report this file";
+
+/// Run `parse`, turning a panic into [`PARSE_PANIC_ERROR`] and logging its message
+pub fn guard_parse<T>(parse: impl FnOnce() -> Result<T, String>) -> Result<T, String> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(parse)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("(no message)");
+        eprintln!("Error: parser panicked: {}", message);
+        Err(PARSE_PANIC_ERROR.to_string())
+    })
+}
+
+/// Leading bytes of a gzip stream
+pub const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+
+/// `buffer` decompressed when it is gzip data (a `carparts.txt.gz` backup),
+/// otherwise unchanged
+pub fn gunzip_if_compressed(buffer: Vec<u8>) -> Result<Vec<u8>, String> {
+    if !buffer.starts_with(&GZIP_MAGIC) {
+        return Ok(buffer);
+    }
+    let mut out = Vec::new();
+    flate2::read::GzDecoder::new(buffer.as_slice())
+        .read_to_end(&mut out)
+        .map_err(|e| e.to_string())?;
+    Ok(out)
+}
+
+/// Read and parse the file at `path`, gzip-compressed or not
+pub fn read_carparts(path: &Path) -> LoadResult {
+    if !path.exists() {
+        let error = format!("File not found: {}", path.display());
+        return (Err(error), ParseDiagnostics::default());
+    }
+    match std::fs::read(path) {
+        Ok(buffer) => parse_carparts(buffer, &path.display().to_string()),
+        Err(e) => (
+            Err(format!("Failed to read {}: {}", path.display(), e)),
+            ParseDiagnostics::default(),
+        ),
+    }
+}
+
+/// Parse the contents of a carparts.txt, gzip-compressed or not; `name`
+/// identifies it in error messages
+pub fn parse_carparts(buffer: Vec<u8>, name: &str) -> LoadResult {
+    let mut diagnostics = ParseDiagnostics::default();
+    let result = match gunzip_if_compressed(buffer) {
+        Ok(buffer) if !looks_like_carparts(&buffer) => Err(format!(
+            "This doesn't look like a carparts.txt (no VIN section in {})",
+            name
+        )),
+        Ok(buffer) => {
+            let result = guard_parse(|| {
+                let vehicles = parse_vin_sections(&buffer, &mut diagnostics);
+                if vehicles.is_empty() {
+                    Err("No VIN data found in file".to_string())
+                } else {
+                    Ok(vehicles)
+                }
+            });
+            diagnostics.buffer = buffer;
+            result
+        }
+        Err(e) => Err(format!("Failed to decompress {}: {}", name, e)),
+    };
+    (result, diagnostics)
+}
+
+/// Read VINGen4 section from carparts.txt, gzip-compressed or not
+pub fn parse_vingen4_file(path: &Path) -> Option<Vec<(String, String)>> {
+    let mut file = File::open(path).ok()?;
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer).ok()?;
+    let buffer = gunzip_if_compressed(buffer).ok()?;
+    if !looks_like_carparts(&buffer) {
+        return None;
+    }
+    parse_vingen4_bytes(&buffer, &mut ParseDiagnostics::default())
+}
+
+/// Read the first VINGen4 section from the raw bytes of a carparts.txt
+pub fn parse_vingen4_bytes(
+    buffer: &[u8],
+    diagnostics: &mut ParseDiagnostics,
+) -> Option<Vec<(String, String)>> {
+    parse_vin_sections(buffer, diagnostics).into_iter().next()
+}
+
+/// Read every VINGen4 section (one per vehicle) from the raw bytes of a
+/// carparts.txt, in file order. The diagnostics describe the first one.
+pub fn parse_vin_sections(
+    buffer: &[u8],
+    diagnostics: &mut ParseDiagnostics,
+) -> Vec<Vec<(String, String)>> {
+    let started = std::time::Instant::now();
+    let mut sections = Vec::new();
+    let mut i = 0;
+    while i < buffer.len() {
+        if buffer[i] != HX_START_ENTRY {
+            i += 1;
+            continue;
+        }
+        if i + 1 >= buffer.len() {
+            break;
+        }
+        let tag_size = buffer[i + 1] as usize;
+        // A corrupt entry doesn't end the scan: step past its start marker and
+        // keep looking, so a later VIN section is still found
+        if i + 2 + tag_size + 4 > buffer.len() {
+            diagnostics.malformed_entries += 1;
+            i += 1;
+            continue;
+        }
+        let tag = String::from_utf8_lossy(&buffer[i + 2..i + 2 + tag_size]);
+        let body_len = u32::from_le_bytes([
+            buffer[i + 2 + tag_size],
+            buffer[i + 3 + tag_size],
+            buffer[i + 4 + tag_size],
+            buffer[i + 5 + tag_size],
+        ]) as usize;
+        let body_start = i + 2 + tag_size + 4;
+        let body_end = body_start + body_len;
+        if body_end > buffer.len() {
+            diagnostics.malformed_entries += 1;
+            i += 1;
+            continue;
+        }
+        diagnostics
+            .sections
+            .push((tag.to_string(), body_start..body_end));
+        if tag == "VINGen4" {
+            let body = &buffer[body_start..body_end];
+            // Only the first vehicle's section is described in the diagnostics
+            let first = sections.is_empty();
+            match read_header(body) {
+                Some((CONTAINER_TYPE_DICTIONARY, ktype, vtype, offset)) => {
+                    let entries = parse_dictionary_vec(
+                        &body[offset..],
+                        ktype,
+                        vtype,
+                        &mut diagnostics.warnings,
+                    );
+                    if first {
+                        diagnostics.vin_tag = Some(tag.to_string());
+                        diagnostics.key_type = Some(ktype);
+                        diagnostics.value_type = Some(vtype);
+                        if vtype == VALUE_TYPE_INT32 {
+                            diagnostics.int32_values = entries
+                                .iter()
+                                .filter_map(|(k, v)| int32_bits(v).map(|bits| (k.clone(), bits)))
+                                .filter(|(_, bits)| *bits > i32::MAX as u32)
+                                .collect();
+                        } else if value_type_name(vtype).is_none() {
+                            diagnostics.unknown_values = entries
+                                .iter()
+                                .filter_map(|(k, v)| {
+                                    let bytes = parse_hex_dump(v).ok()?.try_into().ok()?;
+                                    Some((k.clone(), bytes))
+                                })
+                                .collect();
+                        }
+                    }
+                    sections.push(entries);
+                }
+                Some((CONTAINER_TYPE_NONE, _, vtype, mut offset)) => {
+                    // A single value holds the complete VIN string
+                    if let Some(vin) = parse_value(body, &mut offset, vtype) {
+                        if first {
+                            diagnostics.vin_tag = Some(tag.to_string());
+                            diagnostics.value_type = Some(vtype);
+                        }
+                        sections.push(split_vin_entries(unwrap_typed_value(&clean_entry(&vin)).1));
+                    }
+                }
+                _ => {}
+            }
+        }
+        i = body_end;
+    }
+    diagnostics.parse_time = Some(started.elapsed());
+    sections
+}
+
+/// Entries of one section, or why they couldn't be read
+pub type SectionEntries = Result<Vec<(String, String)>, String>;
+
+/// Key/value pairs of any entry body: a dictionary's entries, a list's values
+/// keyed by index, or a single value keyed "value"
+pub fn parse_section(body: &[u8]) -> SectionEntries {
+    match read_header(body) {
+        Some((CONTAINER_TYPE_DICTIONARY | CONTAINER_TYPE_LIST, ktype, vtype, offset)) => {
+            Ok(parse_dictionary_vec(
+                body.get(offset..).unwrap_or_default(),
+                ktype,
+                vtype,
+                &mut Vec::new(),
+            ))
+        }
+        Some((CONTAINER_TYPE_NONE, _, vtype, mut offset)) => parse_value(body, &mut offset, vtype)
+            .map(|val| vec![("value".to_string(), clean_entry(&val))])
+            .ok_or_else(|| "The section's value is cut off".to_string()),
+        Some((container_type, ..)) => Err(format!(
+            "Unsupported container type 0x{:02X}",
+            container_type
+        )),
+        None => Err("The section header is cut off".to_string()),
+    }
+}
+
+/// Section entries in file order, or by key when `by_key` is set: numeric keys
+/// (list indices) by value, others alphabetically ignoring case
+pub fn section_order(entries: &[(String, String)], by_key: bool) -> Vec<&(String, String)> {
+    let mut ordered: Vec<_> = entries.iter().collect();
+    if by_key {
+        ordered.sort_by_cached_key(|(key, _)| (key.parse::<u64>().ok(), key.to_lowercase()));
+    }
+    ordered
+}
+
+/// Decode a pasted hex dump, tolerating whitespace, commas and `0x` prefixes
+pub fn parse_hex_dump(text: &str) -> Result<Vec<u8>, String> {
+    let digits: String = text
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .map(|token| {
+            token
+                .strip_prefix("0x")
+                .or_else(|| token.strip_prefix("0X"))
+                .unwrap_or(token)
+        })
+        .collect();
+    if let Some(bad) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid hex character: {:?}", bad));
+    }
+    if digits.len() % 2 != 0 {
+        return Err("Hex dump has an odd number of digits".to_string());
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|e| e.to_string()))
+        .collect()
+}
+
+/// Decode VIN entries from a pasted hex dump: either whole entries (starting
+/// with the 0x7E entry marker) or just the body of a VINGen4 section
+pub fn parse_vingen4_hex(
+    text: &str,
+    diagnostics: &mut ParseDiagnostics,
+) -> Result<Vec<(String, String)>, String> {
+    let bytes = parse_hex_dump(text)?;
+    if bytes.is_empty() {
+        return Err("Hex dump is empty".to_string());
+    }
+    if bytes[0] == HX_START_ENTRY {
+        if let Some(entries) = parse_vingen4_bytes(&bytes, diagnostics) {
+            diagnostics.buffer = bytes;
+            return Ok(entries);
+        }
+    }
+    match read_header(&bytes) {
+        Some((ctype, ktype, vtype, offset)) if ctype == CONTAINER_TYPE_DICTIONARY => {
+            Ok(parse_dictionary_vec(
+                &bytes[offset.min(bytes.len())..],
+                ktype,
+                vtype,
+                &mut diagnostics.warnings,
+            ))
+        }
+        _ => Err("No VIN data found in hex dump".to_string()),
+    }
+}
+
+/// VIN field decode tables
+pub fn decode_map() -> DecodeMap {
+    let mut map = HashMap::new();
+    map.insert("Country", HashMap::from_iter([("U", "Corris Britain")]));
+    map.insert(
+        "AssemblyPlant",
+        HashMap::from_iter([
+            ("A", "Dagenham"),
+            ("B", "Manchester"),
+            ("C", "Saarlouis"),
+            ("K", "Rheine"),
+        ]),
+    );
+    map.insert("Model", HashMap::from_iter([("B", "Rivett")]));
+    map.insert("Body", HashMap::from_iter([("B", "2D Pillared Sedan")]));
+    map.insert(
+        "Version",
+        HashMap::from_iter([("D", "L"), ("E", "LX"), ("G", "SLX"), ("P", "GT")]),
+    );
+    map.insert(
+        "Year",
+        HashMap::from_iter([
+            ("L", "1971"),
+            ("M", "1972"),
+            ("N", "1973"),
+            ("P", "1974 (Facelift)"),
+            ("R", "1975"),
+            ("S", "1976"),
+        ]),
+    );
+    map.insert(
+        "Month",
+        HashMap::from_iter([
+            ("C", "01"),
+            ("K", "02"),
+            ("D", "03"),
+            ("E", "04"),
+            ("L", "05"),
+            ("Y", "06"),
+            ("S", "07"),
+            ("T", "08"),
+            ("J", "09"),
+            ("U", "10"),
+            ("M", "11"),
+            ("P", "12"),
+        ]),
+    );
+    map.insert("Drive", HashMap::from_iter([("1", "RWD")]));
+    map.insert(
+        "Engine",
+        HashMap::from_iter([("NA", "Standard 2.0"), ("NE", "High Performance 2.0")]),
+    );
+    map.insert(
+        "Gearbox",
+        HashMap::from_iter([("7", "3-spd Automatic"), ("B", "4-spd Manual")]),
+    );
+    map.insert(
+        "AxleRatio",
+        HashMap::from_iter([
+            ("S", "3.44"),
+            ("B", "3.75"),
+            ("C", "3.89"),
+            ("N", "4.11"),
+            ("E", "4.44"),
+        ]),
+    );
+    map.insert(
+        "AxleLock",
+        HashMap::from_iter([("A", "Open"), ("B", "LSD")]),
+    );
+    map.insert(
+        "ColorsBody",
+        HashMap::from_iter([
+            ("A", "Dark Grey"),
+            ("B", "Nature White"),
+            ("C", "Sand"),
+            ("D", "Asphalt Grey"),
+            ("E", "Blue"),
+            ("F", "Sun Yellow"),
+            ("G", "Dark Navy"),
+            ("H", "Royal Red"),
+            ("I", "Brown"),
+            ("J", "Red"),
+            ("K", "Electric Green"),
+            ("L", "White Pearl"),
+            ("M", "Spring Green"),
+            ("R", "Purple"),
+            ("T", "Yellow"),
+            ("U", "Sky Blue"),
+            ("V", "Orange"),
+            ("X", "Navy Blue"),
+            ("Y", "Special"),
+        ]),
+    );
+    map.insert(
+        "VinylRoof",
+        HashMap::from_iter([
+            ("-", "Paint"),
+            ("A", "Black"),
+            ("B", "White"),
+            ("C", "Tan"),
+            ("K", "Blue"),
+            ("M", "Dark Brown"),
+        ]),
+    );
+    map.insert(
+        "InteriorTrim",
+        HashMap::from_iter([
+            ("N", "Red"),
+            ("A", "Black"),
+            ("K", "Tan"),
+            ("F", "Blue"),
+            ("Y", "Special"),
+        ]),
+    );
+    map.insert(
+        "Radio",
+        HashMap::from_iter([("-", "Radio delete"), ("J", "Radio")]),
+    );
+    map.insert(
+        "InstrumentPanel",
+        HashMap::from_iter([("-", "Standard"), ("G", "Clock"), ("M", "Tachometer")]),
+    );
+    map.insert(
+        "Windshield",
+        HashMap::from_iter([("1", "Clear"), ("2", "Tinted"), ("F", "Sunstrip")]),
+    );
+    map.insert(
+        "Seats",
+        HashMap::from_iter([("8", "Standard"), ("B", "Bucket Style")]),
+    );
+    map.insert(
+        "Suspension",
+        HashMap::from_iter([
+            ("A", "Standard"),
+            ("B", "Standard + Stiffened"),
+            ("4", "Lowered"),
+            ("M", "Lowered + Stiffened"),
+        ]),
+    );
+    map.insert(
+        "PowerBrakes",
+        HashMap::from_iter([("-", "Standard"), ("B", "Power Brakes")]),
+    );
+    map.insert(
+        "Wheels",
+        HashMap::from_iter([
+            ("A", "13\" Steel"),
+            ("B", "13\" Steel + hubcaps"),
+            ("4", "14\" Sport"),
+            ("M", "14\" Steel / 14\" Octo"),
+        ]),
+    );
+    map.insert(
+        "WindowHeater",
+        HashMap::from_iter([
+            ("-", "Standard"),
+            ("B", "Heated"),
+            ("M", "Standard + Window Grille"),
+        ]),
+    );
+    map
+}
+
+/// Vehicle model with its own VIN layout and decode tables
+pub struct VehicleModel {
+    /// Model field code that selects this model
+    pub code: &'static str,
+    /// Human-readable model name
+    pub name: &'static str,
+    /// Ordered VIN fields
+    pub structure: &'static [VinField],
+    /// Builds the model's decode tables
+    pub decode_map: fn() -> DecodeMap,
+    /// Body codes the model was built with
+    pub bodies: &'static [&'static str],
+}
+
+impl VehicleModel {
+    /// Built-in decode tables extended with the codes from vin_tables.toml;
+    /// codes that don't fit their field are left out
+    pub fn tables(&self) -> DecodeMap {
+        let mut map = (self.decode_map)();
+        for (key, codes) in &config::get().codes {
+            let Some(field) = self.structure.iter().find(|f| f.key == key) else {
+                continue;
+            };
+            let table = map.entry(field.key).or_default();
+            for (code, label) in codes {
+                if code.chars().count() == field.len {
+                    table.insert(code.as_str(), label.as_str());
+                }
+            }
+        }
+        map
+    }
+}
+
+/// Known models; the first one is the fallback for unrecognized Model codes
+pub const VEHICLE_MODELS: &[VehicleModel] = &[VehicleModel {
+    code: "B",
+    name: "Rivett",
+    structure: VIN_STRUCTURE,
+    decode_map,
+    bodies: &["B"],
+}];
+
+/// Index into `VEHICLE_MODELS` for a Model field code, `None` when unrecognized
+pub fn model_index_for_code(code: &str) -> Option<usize> {
+    VEHICLE_MODELS.iter().position(|m| m.code == code)
+}
+
+/// Model code of a manually entered VIN (the Model field sits at the same
+/// position for every known layout)
+pub fn model_code_in_vin(vin: &str) -> &str {
+    let mut pos = 0;
+    for field in VIN_STRUCTURE {
+        if field.key == "Model" {
+            return vin.get(pos..pos + field.len).unwrap_or("");
+        }
+        pos += field.len;
+    }
+    ""
+}
+
+/// 1-based character position of each field, e.g. "8-12" for a 5-character field
+pub fn field_positions(structure: &[VinField]) -> Vec<String> {
+    let mut start = 1;
+    structure
+        .iter()
+        .map(|field| {
+            let end = start + field.len - 1;
+            let label = if field.len == 1 {
+                start.to_string()
+            } else {
+                format!("{}-{}", start, end)
+            };
+            start = end + 1;
+            label
+        })
+        .collect()
+}
+
+/// Every field with its known (code, label) options, in VIN order with codes sorted
+pub fn all_field_options(
+    structure: &[VinField],
+    decode_map: &DecodeMap,
+) -> Vec<(&'static str, Vec<(&'static str, &'static str)>)> {
+    structure
+        .iter()
+        .map(|field| {
+            let mut options: Vec<_> = decode_map
+                .get(field.key)
+                .map(|m| m.iter().map(|(&code, &label)| (code, label)).collect())
+                .unwrap_or_default();
+            options.sort_unstable();
+            (field.key, options)
+        })
+        .collect()
+}
+
+/// Whether `query` matches `label`: 2 for a substring, 1 when its characters
+/// appear in order (fuzzy), 0 otherwise; case-insensitive
+pub fn match_rank(label: &str, query: &str) -> u8 {
+    let label = label.to_lowercase();
+    let query = query.to_lowercase();
+    if label.contains(&query) {
+        return 2;
+    }
+    let mut chars = label.chars();
+    if query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .all(|q| chars.any(|c| c == q))
+    {
+        1
+    } else {
+        0
+    }
+}
+
+/// Field options whose label matches `query`, substring matches first, then
+/// in VIN order; empty for a blank query
+pub fn search_options<'s>(
+    structure: &'s [VinField],
+    decode_map: &DecodeMap,
+    query: &str,
+) -> Vec<(&'s VinField, &'static str, &'static str)> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let mut hits: Vec<_> = structure
+        .iter()
+        .zip(all_field_options(structure, decode_map))
+        .flat_map(|(field, (_, options))| {
+            options
+                .into_iter()
+                .map(move |(code, label)| (match_rank(label, query), field, code, label))
+        })
+        .filter(|(rank, ..)| *rank > 0)
+        .collect();
+    // Stable, so VIN order is kept within a rank
+    hits.sort_by_key(|(rank, ..)| std::cmp::Reverse(*rank));
+    hits.into_iter()
+        .map(|(_, field, code, label)| (field, code, label))
+        .collect()
+}
+
+/// Cross-field plausibility rule: when `field` holds one of `codes`,
+/// `other` must hold one of `allowed`
+pub struct ComboRule {
+    pub field: &'static str,
+    pub codes: &'static [&'static str],
+    pub other: &'static str,
+    pub allowed: &'static [&'static str],
+    /// Shown when the rule is violated
+    pub message: &'static str,
+}
+
+/// Known factory pairings
+pub const COMBO_RULES: &[ComboRule] = &[
+    ComboRule {
+        field: "AxleRatio",
+        codes: &["E"],
+        other: "AxleLock",
+        allowed: &["B"],
+        message: "Axle ratio 4.44 was only fitted with the LSD",
+    },
+    ComboRule {
+        field: "AxleRatio",
+        codes: &["E"],
+        other: "Version",
+        allowed: &["P"],
+        message: "Axle ratio 4.44 was only offered on the GT",
+    },
+    ComboRule {
+        field: "AxleLock",
+        codes: &["B"],
+        other: "Version",
+        allowed: &["G", "P"],
+        message: "LSD was only offered on the SLX and GT",
+    },
+    ComboRule {
+        field: "Version",
+        codes: &["P"],
+        other: "ColorsBody",
+        // Dark Grey, Sand, Asphalt Grey and Brown were base-model colors
+        allowed: &[
+            "B", "E", "F", "G", "H", "J", "K", "L", "M", "R", "T", "U", "V", "X", "Y",
+        ],
+        message: "The GT was never painted in a base-model color",
+    },
+    ComboRule {
+        field: "Version",
+        codes: &["P"],
+        other: "InteriorTrim",
+        // The plain black trim was reserved for the L, LX and SLX
+        allowed: &["N", "K", "F", "Y"],
+        message: "The GT was never fitted with the plain black interior",
+    },
+    // Axle ratio and differential are options of the rear axle
+    ComboRule {
+        field: "AxleRatio",
+        codes: &["S", "B", "C", "N", "E"],
+        other: "Drive",
+        allowed: &["1"],
+        message: "Axle ratios were only listed for the rear-wheel-drive axle",
+    },
+    ComboRule {
+        field: "AxleLock",
+        codes: &["B"],
+        other: "Drive",
+        allowed: &["1"],
+        message: "The LSD was only built into the rear-wheel-drive axle",
+    },
+];
+
+/// Check VIN values against the cross-field rules and the known model's
+/// bodies, returns violation messages
+pub fn check_combinations<'a>(get_value: impl Fn(&str) -> &'a str) -> Vec<&'static str> {
+    let mut warnings: Vec<&'static str> = COMBO_RULES
+        .iter()
+        .filter(|rule| {
+            let val = get_value(rule.field);
+            let other = get_value(rule.other);
+            rule.codes.contains(&val) && !other.is_empty() && !rule.allowed.contains(&other)
+        })
+        .map(|rule| rule.message)
+        .collect();
+    let body = get_value("Body");
+    let model = model_index_for_code(get_value("Model")).map(|i| &VEHICLE_MODELS[i]);
+    if model.is_some_and(|m| !body.is_empty() && !m.bodies.contains(&body)) {
+        warnings.push("This body type was never built for the model");
+    }
+    warnings
+}
+
+/// Split VIN string into fields
+pub fn parse_vin(vin: &str, structure: &[VinField]) -> HashMap<String, String> {
+    let mut pos = 0;
+    structure
+        .iter()
+        .map(|field| {
+            let end = pos + field.len;
+            let val = vin.get(pos..end).unwrap_or("").to_string();
+            pos = end;
+            (field.key.to_string(), val)
+        })
+        .collect()
+}
+
+/// Code of a field's decoded label (reverse lookup). When several codes share
+/// the label the first by code order wins and the ambiguity is logged.
+pub fn encode_field(decode_map: &DecodeMap, field: &str, label: &str) -> Option<&'static str> {
+    let mut codes: Vec<&'static str> = decode_map
+        .get(field)?
+        .iter()
+        .filter(|(_, &l)| l == label)
+        .map(|(&code, _)| code)
+        .collect();
+    codes.sort_unstable();
+    if codes.len() > 1 {
+        eprintln!(
+            "Warning: {} label {:?} has codes {}, using {}",
+            field,
+            label,
+            codes.join("/"),
+            codes[0]
+        );
+    }
+    codes.first().copied()
+}
+
+/// Decode a complete VIN string with the layout of its model, without the GUI.
+/// The VIN is trimmed and uppercased first (see [`normalize_code`]).
+pub fn decode_vin(vin: &str, decode_map: &DecodeMap) -> DecodedVin<'static> {
+    let vin = normalize_code(vin, false);
+    let vin = vin.as_ref();
+    let model = model_index_for_code(model_code_in_vin(vin)).unwrap_or(0);
+    DecodedVin::from_vin(VEHICLE_MODELS[model].structure, decode_map, vin)
+}
+
+/// Fields of `vin` that decode, counting a numeric serial
+pub fn decodable_fields(vin: &str, structure: &[VinField], decode_map: &DecodeMap) -> usize {
+    decoded_fields(&DecodedVin::from_vin(structure, decode_map, vin))
+}
+
+/// Points lost per field that doesn't decode
+pub const UNKNOWN_FIELD_PENALTY: usize = 8;
+/// Points lost per failed cross-field rule
+pub const COMBO_RULE_PENALTY: usize = 10;
+
+/// 0-100 rating of how trustworthy a decode is: 100 when every field decodes
+/// and no combination rule fails, minus [`UNKNOWN_FIELD_PENALTY`] per field
+/// that doesn't and [`COMBO_RULE_PENALTY`] per failed rule
+pub fn decode_confidence(decoded: &DecodedVin) -> u8 {
+    let unknown = decoded.structure().len() - decoded_fields(decoded);
+    let rules = check_combinations(|key| decoded.value(key)).len();
+    let penalty = unknown * UNKNOWN_FIELD_PENALTY + rules * COMBO_RULE_PENALTY;
+    100usize.saturating_sub(penalty) as u8
+}
+
+/// Placeholder for a redacted serial
+pub const REDACTED_SERIAL: &str = "XXXXX";
+
+/// Fields that decode, counting a numeric or redacted serial
+pub fn decoded_fields(decoded: &DecodedVin) -> usize {
+    decoded
+        .iter()
+        .filter(|(key, _, val, status)| match status {
+            FieldStatus::Decoded(_) | FieldStatus::Standard => true,
+            FieldStatus::Raw => {
+                *key == "Serial"
+                    && (*val == REDACTED_SERIAL
+                        || (!val.is_empty() && val.chars().all(|c| c.is_ascii_digit())))
+            }
+            _ => false,
+        })
+        .count()
+}
+
+/// Character inserted by an alignment guess; a digit, since the usual slip is
+/// a miscounted Serial
+pub const ALIGNMENT_FILLER: char = '0';
+
+/// One way to fix a VIN that is a character too long or too short
+#[derive(Debug)]
+pub struct Alignment {
+    /// VIN with the edit applied
+    pub vin: String,
+    /// What was changed, e.g. "remove \"7\" at position 9"
+    pub edit: String,
+    /// `vin` split into its fields with "|", showing where each lands
+    pub fields: String,
+    /// Fields that decode with this alignment
+    pub score: usize,
+}
+
+/// Alignment guesses shown under a VIN length error
+pub struct AlignmentHints {
+    pub list: Vec<Alignment>,
+    /// Index of the guess decoding the most fields
+    pub best: usize,
+    /// Number of fields of the layout, for "n/total fields decode"
+    pub total: usize,
+}
+
+/// Every single-character removal (VIN one too long) or insertion of
+/// `ALIGNMENT_FILLER` (one too short), in position order; empty for other lengths
+pub fn alignments(vin: &str, structure: &[VinField], decode_map: &DecodeMap) -> Vec<Alignment> {
+    let vin_len: usize = structure.iter().map(|f| f.len).sum();
+    let chars: Vec<char> = vin.chars().collect();
+    let edits: Vec<(String, String)> = if chars.len() == vin_len + 1 {
+        (0..chars.len())
+            .map(|i| {
+                let mut edited = chars.clone();
+                let removed = edited.remove(i);
+                let edit = format!("remove \"{}\" at position {}", removed, i + 1);
+                (edited.into_iter().collect(), edit)
+            })
+            .collect()
+    } else if chars.len() + 1 == vin_len {
+        (0..=chars.len())
+            .map(|i| {
+                let mut edited = chars.clone();
+                edited.insert(i, ALIGNMENT_FILLER);
+                let edit = format!("insert \"{}\" at position {}", ALIGNMENT_FILLER, i + 1);
+                (edited.into_iter().collect(), edit)
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    edits
+        .into_iter()
+        .map(|(vin, edit)| {
+            let fields = parse_vin(&vin, structure);
+            Alignment {
+                score: decodable_fields(&vin, structure, decode_map),
+                fields: structure
+                    .iter()
+                    .map(|f| fields.get(f.key).map_or("", String::as_str))
+                    .collect::<Vec<_>>()
+                    .join("|"),
+                vin,
+                edit,
+            }
+        })
+        .collect()
+}
+
+/// Best VIN-shaped substring of `text` for one layout with its score (fields
+/// that decode plus a numeric serial). Windows of the VIN's length slide over
+/// runs of code characters; ties go to the earliest match.
+pub fn find_vin_in_text(
+    text: &str,
+    structure: &[VinField],
+    decode_map: &DecodeMap,
+) -> Option<(String, usize)> {
+    let vin_len: usize = structure.iter().map(|f| f.len).sum();
+    let chars: Vec<char> = text.chars().collect();
+    let mut best: Option<(String, usize)> = None;
+    for start in 0..chars.len().saturating_sub(vin_len - 1) {
+        let window = &chars[start..start + vin_len];
+        if !window
+            .iter()
+            .all(|c| c.is_ascii_alphanumeric() || *c == '-')
+        {
+            continue;
+        }
+        let candidate: String = window.iter().collect();
+        let score = decodable_fields(&candidate, structure, decode_map);
+        if best.as_ref().map_or(true, |(_, s)| score > *s) {
+            best = Some((candidate, score));
+        }
+    }
+    // Mostly decodable, so random words and numbers don't count
+    best.filter(|(_, score)| score * 4 >= structure.len() * 3)
+}
+
+/// Codes as they are looked up: trimmed, and uppercased unless codes are
+/// case-sensitive
+pub fn normalize_code(code: &str, case_sensitive: bool) -> Cow<'_, str> {
+    let code = code.trim();
+    if case_sensitive || !code.chars().any(|c| c.is_lowercase()) {
+        Cow::Borrowed(code)
+    } else {
+        Cow::Owned(code.to_uppercase())
+    }
+}
+
+/// Split pipe- or whitespace-separated VIN input into tokens, e.g. "U|A|B|B|D|N|..."
+pub fn vin_tokens(input: &str) -> Vec<&str> {
+    input
+        .split(|c: char| c == '|' || c.is_whitespace())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+/// Map one token per field positionally, checking each token against the field length
+pub fn parse_delimited_vin(
+    tokens: &[&str],
+    structure: &[VinField],
+) -> Result<HashMap<String, String>, String> {
+    if tokens.len() != structure.len() {
+        return Err(format!(
+            "Expected {} fields, got {}",
+            structure.len(),
+            tokens.len()
+        ));
+    }
+    structure
+        .iter()
+        .zip(tokens)
+        .enumerate()
+        .map(|(i, (field, token))| {
+            if token.chars().count() == field.len {
+                Ok((field.key.to_string(), token.to_string()))
+            } else {
+                Err(format!(
+                    "Field {} ({}) should be {} characters, got \"{}\"",
+                    i + 1,
+                    field.display,
+                    field.len,
+                    token
+                ))
+            }
+        })
+        .collect()
+}
+
+/// Typed VIN split into the fields of its model
+pub struct ParsedInput {
+    /// Input with separators removed
+    pub vin: String,
+    /// Index into `VEHICLE_MODELS`
+    pub model: usize,
+    pub structure: &'static [VinField],
+    pub entries: HashMap<String, String>,
+}
+
+/// Split typed VIN input into field values; `Ok(None)` when it is empty.
+/// `model_for` picks the model (index into `VEHICLE_MODELS`) for its Model code.
+pub fn parse_vin_input(
+    input: &str,
+    case_sensitive: bool,
+    model_for: impl Fn(&str) -> usize,
+) -> Result<Option<ParsedInput>, String> {
+    let input = normalize_code(input, case_sensitive);
+    let tokens = vin_tokens(&input);
+    let vin = tokens.concat();
+    if vin.is_empty() {
+        return Ok(None);
+    }
+    let model = model_for(model_code_in_vin(&vin));
+    let structure = VEHICLE_MODELS[model].structure;
+    let vin_len: usize = structure.iter().map(|f| f.len).sum();
+    // One token per field (or explicit pipes) maps positionally, anything
+    // else is sliced by field width with the spaces removed
+    let delimited = input.contains('|') || tokens.len() == structure.len();
+    let entries = if delimited {
+        parse_delimited_vin(&tokens, structure)?
+    } else if vin.len() != vin_len {
+        return Err(format!(
+            "Invalid VIN length: {} characters (expected {})",
+            vin.len(),
+            vin_len
+        ));
+    } else {
+        parse_vin(&vin, structure)
+    };
+    Ok(Some(ParsedInput {
+        vin,
+        model,
+        structure,
+        entries,
+    }))
+}
+
+/// Scheme and path of links that open the decoder on a VIN
+pub const DEEP_LINK_PREFIX: &str = "mwcvin://decode/";
+
+/// Link that decodes `vin` when opened, `None` unless `vin` is a complete,
+/// well-formed VIN (letters, digits and "-" only)
+pub fn deep_link(vin: &str) -> Option<String> {
+    if !vin.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return None;
+    }
+    let parsed = parse_vin_input(vin, true, |code| model_index_for_code(code).unwrap_or(0));
+    match parsed {
+        Ok(Some(parsed)) => Some(format!("{}{}", DEEP_LINK_PREFIX, parsed.vin)),
+        _ => None,
+    }
+}
+
+/// VIN of a link made by [`deep_link`]
+pub fn vin_from_deep_link(link: &str) -> Option<&str> {
+    let vin = link.strip_prefix(DEEP_LINK_PREFIX)?.trim_end_matches('/');
+    (!vin.is_empty() && vin.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')).then_some(vin)
+}
+
+/// Get plain RGB color for field code (no GUI dependency)
+pub fn color_rgb_for_code(field: &str, code: &str) -> Option<[u8; 3]> {
+    match field {
+        "ColorsBody" => match code {
+            "A" => Some([64, 64, 64]),    // Dark Grey
+            "B" => Some([240, 240, 240]), // Nature White
+            "C" => Some([210, 180, 140]), // Sand
+            "D" => Some([80, 80, 80]),    // Asphalt Grey
+            "E" => Some([0, 80, 200]),    // Blue
+            "F" => Some([255, 220, 40]),  // Sun Yellow
+            "G" => Some([10, 10, 60]),    // Dark Navy
+            "H" => Some([180, 0, 0]),     // Royal Red
+            "I" => Some([120, 80, 40]),   // Brown
+            "J" => Some([200, 0, 0]),     // Red
+            "K" => Some([0, 200, 80]),    // Electric Green
+            "L" => Some([255, 255, 255]), // White Pearl
+            "M" => Some([120, 255, 120]), // Spring Green
+            "R" => Some([160, 0, 160]),   // Purple
+            "T" => Some([255, 255, 0]),   // Yellow
+            "U" => Some([120, 180, 255]), // Sky Blue
+            "V" => Some([255, 120, 0]),   // Orange
+            "X" => Some([0, 0, 120]),     // Navy Blue
+            "Y" => Some([212, 175, 55]),  // Special (gold)
+            _ => None,
+        },
+        "VinylRoof" => match code {
+            "-" => Some([200, 200, 200]), // Paint
+            "A" => Some([20, 20, 20]),    // Black
+            "B" => Some([255, 255, 255]), // White
+            "C" => Some([210, 180, 140]), // Tan
+            "K" => Some([0, 80, 200]),    // Blue
+            "M" => Some([80, 40, 20]),    // Dark Brown
+            _ => None,
+        },
+        "InteriorTrim" => match code {
+            "N" => Some([200, 0, 0]),     // Red
+            "A" => Some([20, 20, 20]),    // Black
+            "K" => Some([210, 180, 140]), // Tan
+            "F" => Some([0, 80, 200]),    // Blue
+            "Y" => Some([212, 175, 55]),  // Special (gold)
+            _ => None,
+        },
+        _ => None,
+    }
+}
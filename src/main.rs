@@ -1,757 +1,2739 @@
-#![windows_subsystem = "windows"]
+// Windowed in release builds; debug builds and the "console" feature keep
+// the console for log output
+#![cfg_attr(
+    all(not(debug_assertions), not(feature = "console")),
+    windows_subsystem = "windows"
+)]
 
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::Read;
-
-use byteorder::LittleEndian;
-use byteorder::ReadBytesExt;
-
-/// Empty string constant
-const EMPTY: &str = "";
-/// Entry start byte in binary format
-const HX_START_ENTRY: u8 = 0x7E;
-
-// VINGen4 binary format type identifiers
-const CONTAINER_TYPE_DICTIONARY: u8 = 0x52;
-const VALUE_TYPE_STRING: u32 = 0xFDE9F1EE;
-const VALUE_TYPE_INT32: u32 = 0xE2A80856;
-const VALUE_TYPE_BOOL: u32 = 0xAD4D7C9C;
-
-/// VIN field definition (key, display name, length)
-#[derive(Debug)]
-struct VinField {
-    /// Field key for lookup
-    key: &'static str,
-    /// Human-readable name
-    display: &'static str,
-    /// Field length in VIN
-    len: usize,
-}
+use std::path::{Path, PathBuf};
+
+use mwc_vin_decoder::codes::{Drive, FieldCode, Version};
+use mwc_vin_decoder::decoded::{field_status, share_code, DecodeSource, DecodedVin, FieldStatus};
+use mwc_vin_decoder::export::ExportFormat;
+use mwc_vin_decoder::{
+    alignments, all_field_options, check_combinations, color_rgb_for_code, config,
+    decode_confidence, deep_link, encode_field, export, field_positions, find_vin_in_text,
+    format_float, guard_parse, model_code_in_vin, model_index_for_code, normalize_code,
+    parse_carparts, parse_section, parse_vin_input, parse_vingen4_hex, read_carparts,
+    round_typed_float, search_options, section_order, unwrap_typed_value, vin_from_deep_link,
+    vin_tokens, AlignmentHints, DecodeMap, LoadResult, ParseDiagnostics, ParsedInput,
+    SectionEntries, VehicleModel, Vehicles, VinField, COMBO_RULE_PENALTY, DEFAULT_FLOAT_DECIMALS,
+    EMPTY, UNKNOWN_FIELD_PENALTY, VEHICLE_MODELS,
+};
+
+mod cli;
+mod save;
+mod settings;
 
 /// Tracks VIN data source
+#[derive(Clone, Copy)]
 enum LastSource {
     None,
     File,
     Vin,
 }
 
-/// Ordered VIN field structure
-const VIN_STRUCTURE: &[VinField] = &[
-    VinField {
-        key: "Country",
-        display: "Country",
-        len: 1,
-    },
-    VinField {
-        key: "AssemblyPlant",
-        display: "Assembly Plant",
-        len: 1,
-    },
-    VinField {
-        key: "Model",
-        display: "Model",
-        len: 1,
-    },
-    VinField {
-        key: "Body",
-        display: "Body",
-        len: 1,
-    },
-    VinField {
-        key: "Version",
-        display: "Version",
-        len: 1,
-    },
-    VinField {
-        key: "Year",
-        display: "Year",
-        len: 1,
-    },
-    VinField {
-        key: "Month",
-        display: "Month",
-        len: 1,
-    },
-    VinField {
-        key: "Serial",
-        display: "Serial",
-        len: 5,
-    },
-    VinField {
-        key: "Drive",
-        display: "Drive",
-        len: 1,
-    },
-    VinField {
-        key: "Engine",
-        display: "Engine",
-        len: 2,
-    },
-    VinField {
-        key: "Gearbox",
-        display: "Gearbox",
-        len: 1,
-    },
-    VinField {
-        key: "AxleRatio",
-        display: "Axle Ratio",
-        len: 1,
-    },
-    VinField {
-        key: "AxleLock",
-        display: "Axle Lock",
-        len: 1,
-    },
-    VinField {
-        key: "ColorsBody",
-        display: "Body Colour",
-        len: 1,
-    },
-    VinField {
-        key: "VinylRoof",
-        display: "Vinyl Roof",
-        len: 1,
-    },
-    VinField {
-        key: "InteriorTrim",
-        display: "Interior Trim",
-        len: 1,
-    },
-    VinField {
-        key: "Radio",
-        display: "Radio",
-        len: 1,
-    },
-    VinField {
-        key: "InstrumentPanel",
-        display: "Instrument Panel",
-        len: 1,
-    },
-    VinField {
-        key: "Windshield",
-        display: "Windshield",
-        len: 1,
-    },
-    VinField {
-        key: "Seats",
-        display: "Seats",
-        len: 1,
-    },
-    VinField {
-        key: "Suspension",
-        display: "Suspension",
-        len: 1,
-    },
-    VinField {
-        key: "PowerBrakes",
-        display: "Brakes",
-        len: 1,
-    },
-    VinField {
-        key: "Wheels",
-        display: "Wheels",
-        len: 1,
-    },
-    VinField {
-        key: "WindowHeater",
-        display: "Rear Window",
-        len: 1,
-    },
-];
-
-/// Parse VINGen4 header, returns (container_type, key_type, value_type, offset)
-fn read_header(body: &[u8]) -> Option<(u8, u32, u32, usize)> {
-    let mut offset = 1;
-    let container_type = if body[0] != 0xFF { body[0] } else { 0x00 };
-    offset += 1;
-    let mut key_type = 0u32;
-    if container_type == CONTAINER_TYPE_DICTIONARY {
-        key_type = (&body[offset..offset + 4])
-            .read_u32::<LittleEndian>()
-            .ok()?;
-        offset += 4;
-    }
-    let value_type = (&body[offset..offset + 4])
-        .read_u32::<LittleEndian>()
-        .ok()?;
-    offset += 4;
-    let prop_size = if key_type == 0 { 1 } else { 2 };
-    offset += prop_size;
-    Some((container_type, key_type, value_type, offset))
-}
-
-/// Parse value from binary (string, int32, bool, or hex)
-fn parse_value(data: &[u8], offset: &mut usize, value_type: u32) -> Option<String> {
-    match value_type {
-        VALUE_TYPE_STRING => {
-            let strlen = data.get(*offset).copied()? as usize;
-            let s = std::str::from_utf8(&data[*offset + 1..*offset + 1 + strlen])
-                .unwrap_or("")
-                .to_string();
-            *offset += 1 + strlen;
-            Some(s)
-        }
-        VALUE_TYPE_INT32 => {
-            if *offset + 4 > data.len() {
-                return None;
-            }
-            let val = (&data[*offset..*offset + 4])
-                .read_i32::<LittleEndian>()
-                .ok()?;
-            *offset += 4;
-            Some(val.to_string())
-        }
-        VALUE_TYPE_BOOL => {
-            let b = data.get(*offset).copied()? != 0;
-            *offset += 1;
-            Some(if b { "true" } else { "false" }.to_string())
-        }
-        _ => {
-            if *offset + 4 > data.len() {
-                return None;
-            }
-            let hex = data[*offset..*offset + 4]
-                .iter()
-                .map(|b| format!("{:02x}", b))
-                .collect::<String>();
-            *offset += 4;
-            Some(hex)
+/// Shown color of a color field; a painted vinyl roof takes the body color
+fn field_rgb<'a>(
+    field_key: &str,
+    val: &str,
+    body_color_getter: impl FnOnce() -> Option<&'a str>,
+) -> Option<[u8; 3]> {
+    if field_key == "VinylRoof" && val == "-" {
+        if let Some(body_val) = body_color_getter() {
+            return color_rgb_for_code("ColorsBody", body_val);
         }
     }
+    color_rgb_for_code(field_key, val)
 }
 
-/// Parse binary dictionary into key-value pairs
-fn parse_dictionary_vec(data: &[u8], key_type: u32, value_type: u32) -> Vec<(String, String)> {
-    if data.len() < 4 {
-        return vec![];
+/// Render color swatch for color fields
+fn render_color_swatch<'a>(
+    ui: &mut egui::Ui,
+    field_key: &str,
+    val: &str,
+    body_color_getter: impl FnOnce() -> Option<&'a str>,
+) {
+    if let Some([r, g, b]) = field_rgb(field_key, val, body_color_getter) {
+        let (rect, _resp) = ui.allocate_exact_size(egui::vec2(18.0, 18.0), egui::Sense::hover());
+        // Outlined so black and white swatches stand out on either theme
+        ui.painter().rect(
+            rect,
+            3.0,
+            egui::Color32::from_rgb(r, g, b),
+            ui.visuals().widgets.noninteractive.bg_stroke,
+            egui::StrokeKind::Inside,
+        );
     }
-    let count = (&data[0..4]).read_u32::<LittleEndian>().unwrap_or(0) as usize;
-    let mut offset = 4;
-    (0..count)
-        .map(|_| {
-            let key = parse_value(data, &mut offset, key_type).unwrap_or_default();
-            let val = parse_value(data, &mut offset, value_type).unwrap_or_default();
-            (key, val)
-        })
-        .collect()
 }
 
-/// Read VINGen4 section from carparts.txt
-fn parse_vingen4_file(path: &str) -> Option<Vec<(String, String)>> {
-    let mut file = File::open(path).ok()?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer).ok()?;
-    let mut i = 0;
-    while i < buffer.len() {
-        if buffer[i] != HX_START_ENTRY {
-            i += 1;
-            continue;
-        }
-        if i + 1 >= buffer.len() {
-            break;
-        }
-        let tag_size = buffer[i + 1] as usize;
-        if i + 2 + tag_size + 4 > buffer.len() {
-            break;
-        }
-        let tag = String::from_utf8_lossy(&buffer[i + 2..i + 2 + tag_size]);
-        let body_len = u32::from_le_bytes([
-            buffer[i + 2 + tag_size],
-            buffer[i + 3 + tag_size],
-            buffer[i + 4 + tag_size],
-            buffer[i + 5 + tag_size],
-        ]) as usize;
-        let body_start = i + 2 + tag_size + 4;
-        let body_end = body_start + body_len;
-        if body_end > buffer.len() {
-            break;
-        }
-        if tag == "VINGen4" {
-            let body = &buffer[body_start..body_end];
-            if let Some((ctype, ktype, vtype, offset)) = read_header(body) {
-                if ctype == CONTAINER_TYPE_DICTIONARY {
-                    let dict = parse_dictionary_vec(&body[offset..], ktype, vtype);
-                    return Some(dict);
-                }
+/// Right-click menu with copy actions for one result row
+fn row_context_menu(
+    response: &egui::Response,
+    field: &VinField,
+    val: &str,
+    status: &str,
+    rgb: Option<[u8; 3]>,
+) {
+    response.context_menu(|ui| {
+        let mut copy = None;
+        if ui.button("Copy code").clicked() {
+            copy = Some(val.to_string());
+        }
+        if !status.is_empty() && ui.button("Copy decoded").clicked() {
+            copy = Some(status.to_string());
+        }
+        if ui.button("Copy this field as text").clicked() {
+            copy = Some(if status.is_empty() {
+                format!("{}: {}", field.display, val)
+            } else {
+                format!("{}: {} ({})", field.display, status, val)
+            });
+        }
+        if let Some([r, g, b]) = rgb {
+            if ui.button("Copy color hex").clicked() {
+                copy = Some(format!("#{:02X}{:02X}{:02X}", r, g, b));
             }
         }
-        i = body_end;
-    }
-    None
-}
-
-/// VIN field decode tables
-fn decode_map() -> HashMap<&'static str, HashMap<&'static str, &'static str>> {
-    let mut map = HashMap::new();
-    map.insert("Country", HashMap::from_iter([("U", "Corris Britain")]));
-    map.insert(
-        "AssemblyPlant",
-        HashMap::from_iter([
-            ("A", "Dagenham"),
-            ("B", "Manchester"),
-            ("C", "Saarlouis"),
-            ("K", "Rheine"),
-        ]),
-    );
-    map.insert("Model", HashMap::from_iter([("B", "Rivett")]));
-    map.insert("Body", HashMap::from_iter([("B", "2D Pillared Sedan")]));
-    map.insert(
-        "Version",
-        HashMap::from_iter([("D", "L"), ("E", "LX"), ("G", "SLX"), ("P", "GT")]),
-    );
-    map.insert(
-        "Year",
-        HashMap::from_iter([
-            ("L", "1971"),
-            ("M", "1972"),
-            ("N", "1973"),
-            ("P", "1974 (Facelift)"),
-            ("R", "1975"),
-            ("S", "1976"),
-        ]),
-    );
-    map.insert(
-        "Month",
-        HashMap::from_iter([
-            ("C", "01"),
-            ("K", "02"),
-            ("D", "03"),
-            ("E", "04"),
-            ("L", "05"),
-            ("Y", "06"),
-            ("S", "07"),
-            ("T", "08"),
-            ("J", "09"),
-            ("U", "10"),
-            ("M", "11"),
-            ("P", "12"),
-        ]),
-    );
-    map.insert("Drive", HashMap::from_iter([("1", "RWD")]));
-    map.insert(
-        "Engine",
-        HashMap::from_iter([("NA", "Standard 2.0"), ("NE", "High Performance 2.0")]),
-    );
-    map.insert(
-        "Gearbox",
-        HashMap::from_iter([("7", "3-spd Automatic"), ("B", "4-spd Manual")]),
-    );
-    map.insert(
-        "AxleRatio",
-        HashMap::from_iter([
-            ("S", "3.44"),
-            ("B", "3.75"),
-            ("C", "3.89"),
-            ("N", "4.11"),
-            ("E", "4.44"),
-        ]),
-    );
-    map.insert(
-        "AxleLock",
-        HashMap::from_iter([("A", "Open"), ("B", "LSD")]),
-    );
-    map.insert(
-        "ColorsBody",
-        HashMap::from_iter([
-            ("A", "Dark Grey"),
-            ("B", "Nature White"),
-            ("C", "Sand"),
-            ("D", "Asphalt Grey"),
-            ("E", "Blue"),
-            ("F", "Sun Yellow"),
-            ("G", "Dark Navy"),
-            ("H", "Royal Red"),
-            ("I", "Brown"),
-            ("J", "Red"),
-            ("K", "Electric Green"),
-            ("L", "White Pearl"),
-            ("M", "Spring Green"),
-            ("R", "Purple"),
-            ("T", "Yellow"),
-            ("U", "Sky Blue"),
-            ("V", "Orange"),
-            ("X", "Navy Blue"),
-            ("Y", "Special"),
-        ]),
-    );
-    map.insert(
-        "VinylRoof",
-        HashMap::from_iter([
-            ("-", "Paint"),
-            ("A", "Black"),
-            ("B", "White"),
-            ("C", "Tan"),
-            ("K", "Blue"),
-            ("M", "Dark Brown"),
-        ]),
-    );
-    map.insert(
-        "InteriorTrim",
-        HashMap::from_iter([
-            ("N", "Red"),
-            ("A", "Black"),
-            ("K", "Tan"),
-            ("F", "Blue"),
-            ("Y", "Special"),
-        ]),
-    );
-    map.insert(
-        "Radio",
-        HashMap::from_iter([("-", "Radio delete"), ("J", "Radio")]),
-    );
-    map.insert(
-        "InstrumentPanel",
-        HashMap::from_iter([("-", "Standard"), ("G", "Clock"), ("M", "Tachometer")]),
-    );
-    map.insert(
-        "Windshield",
-        HashMap::from_iter([("1", "Clear"), ("2", "Tinted"), ("F", "Sunstrip")]),
-    );
-    map.insert(
-        "Seats",
-        HashMap::from_iter([("8", "Standard"), ("B", "Bucket Style")]),
-    );
-    map.insert(
-        "Suspension",
-        HashMap::from_iter([
-            ("A", "Standard"),
-            ("B", "Standard + Stiffened"),
-            ("4", "Lowered"),
-            ("M", "Lowered + Stiffened"),
-        ]),
-    );
-    map.insert(
-        "PowerBrakes",
-        HashMap::from_iter([("-", "Standard"), ("B", "Power Brakes")]),
-    );
-    map.insert(
-        "Wheels",
-        HashMap::from_iter([
-            ("A", "13\" Steel"),
-            ("B", "13\" Steel + hubcaps"),
-            ("4", "14\" Sport"),
-            ("M", "14\" Steel / 14\" Octo"),
-        ]),
-    );
-    map.insert(
-        "WindowHeater",
-        HashMap::from_iter([
-            ("-", "Standard"),
-            ("B", "Heated"),
-            ("M", "Standard + Window Grille"),
-        ]),
-    );
-    map
+        if let Some(text) = copy {
+            ui.ctx().copy_text(text);
+            ui.close();
+        }
+    });
 }
 
-/// Split VIN string into fields
-fn parse_vin(vin: &str) -> HashMap<String, String> {
-    let mut pos = 0;
-    VIN_STRUCTURE
+/// Hover text for a field name: its character range plus the community description
+fn field_tooltip(structure: &[VinField], field: &VinField) -> String {
+    let position = structure
         .iter()
-        .map(|field| {
-            let end = pos + field.len;
-            let val = vin.get(pos..end).unwrap_or("").to_string();
-            pos = end;
-            (field.key.to_string(), val)
-        })
-        .collect()
+        .zip(field_positions(structure))
+        .find(|(f, _)| f.key == field.key)
+        .map(|(_, pos)| pos);
+    let mut text = match position {
+        Some(pos) if field.len == 1 => format!("Character {}", pos),
+        Some(pos) => format!("Characters {}", pos),
+        None => String::new(),
+    };
+    if let Some(description) = field.description() {
+        if !text.is_empty() {
+            text.push_str("\n\n");
+        }
+        text.push_str(description);
+    }
+    text
 }
 
-/// Get color for field code (for GUI swatches)
-fn color_for_code_with_field(field: &str, code: &str) -> Option<egui::Color32> {
-    match field {
-        "ColorsBody" => match code {
-            "A" => Some(egui::Color32::from_rgb(64, 64, 64)), // Dark Grey
-            "B" => Some(egui::Color32::from_rgb(240, 240, 240)), // Nature White
-            "C" => Some(egui::Color32::from_rgb(210, 180, 140)), // Sand
-            "D" => Some(egui::Color32::from_rgb(80, 80, 80)), // Asphalt Grey
-            "E" => Some(egui::Color32::from_rgb(0, 80, 200)), // Blue
-            "F" => Some(egui::Color32::from_rgb(255, 220, 40)), // Sun Yellow
-            "G" => Some(egui::Color32::from_rgb(10, 10, 60)), // Dark Navy
-            "H" => Some(egui::Color32::from_rgb(180, 0, 0)),  // Royal Red
-            "I" => Some(egui::Color32::from_rgb(120, 80, 40)), // Brown
-            "J" => Some(egui::Color32::from_rgb(200, 0, 0)),  // Red
-            "K" => Some(egui::Color32::from_rgb(0, 200, 80)), // Electric Green
-            "L" => Some(egui::Color32::from_rgb(255, 255, 255)), // White Pearl
-            "M" => Some(egui::Color32::from_rgb(120, 255, 120)), // Spring Green
-            "R" => Some(egui::Color32::from_rgb(160, 0, 160)), // Purple
-            "T" => Some(egui::Color32::from_rgb(255, 255, 0)), // Yellow
-            "U" => Some(egui::Color32::from_rgb(120, 180, 255)), // Sky Blue
-            "V" => Some(egui::Color32::from_rgb(255, 120, 0)), // Orange
-            "X" => Some(egui::Color32::from_rgb(0, 0, 120)),  // Navy Blue
-            "Y" => Some(egui::Color32::from_rgb(212, 175, 55)), // Special (gold)
-            _ => None,
-        },
-        "VinylRoof" => match code {
-            "-" => Some(egui::Color32::from_rgb(200, 200, 200)), // Paint
-            "A" => Some(egui::Color32::from_rgb(20, 20, 20)),    // Black
-            "B" => Some(egui::Color32::from_rgb(255, 255, 255)), // White
-            "C" => Some(egui::Color32::from_rgb(210, 180, 140)), // Tan
-            "K" => Some(egui::Color32::from_rgb(0, 80, 200)),    // Blue
-            "M" => Some(egui::Color32::from_rgb(80, 40, 20)),    // Dark Brown
-            _ => None,
-        },
-        "InteriorTrim" => match code {
-            "N" => Some(egui::Color32::from_rgb(200, 0, 0)), // Red
-            "A" => Some(egui::Color32::from_rgb(20, 20, 20)), // Black
-            "K" => Some(egui::Color32::from_rgb(210, 180, 140)), // Tan
-            "F" => Some(egui::Color32::from_rgb(0, 80, 200)), // Blue
-            "Y" => Some(egui::Color32::from_rgb(212, 175, 55)), // Special (gold)
-            _ => None,
-        },
-        _ => None,
-    }
+/// Shown above a decode loaded from the default path at startup
+const AUTO_LOAD_NOTE: &str = "Loaded your game's VIN automatically";
+
+/// Placeholder for the results area before anything is loaded or typed
+fn render_welcome(ui: &mut egui::Ui) {
+    ui.vertical_centered(|ui| {
+        ui.strong("Welcome!");
+        ui.add_space(4.0);
+        ui.add(
+            egui::Label::new(
+                "Load your carparts.txt above (Browse... or drop the file on this window), \
+                 or type a VIN to decode it. The game writes carparts.txt after the first \
+                 save, to AppData\\LocalLow\\Amistech\\My Winter Car in your user folder.",
+            )
+            .wrap(),
+        );
+    });
 }
 
-/// Render color swatch for color fields
-fn render_color_swatch<'a>(
-    ui: &mut egui::Ui,
-    field_key: &str,
-    val: &str,
-    body_color_getter: impl FnOnce() -> Option<&'a str>,
-) {
-    if matches!(field_key, "ColorsBody" | "VinylRoof" | "InteriorTrim") {
-        let mut color = color_for_code_with_field(field_key, val);
-        // VinylRoof = Paint uses body color
-        if field_key == "VinylRoof" && val == "-" {
-            if let Some(body_val) = body_color_getter() {
-                color = color_for_code_with_field("ColorsBody", body_val);
-            }
-        }
-        if let Some(color) = color {
-            let (rect, _resp) =
-                ui.allocate_exact_size(egui::vec2(18.0, 18.0), egui::Sense::hover());
-            ui.painter().rect_filled(rect, 3.0, color);
-        }
+/// About/help contents: version, VIN format notes and field layout
+fn render_about(ui: &mut egui::Ui) {
+    ui.strong(format!(
+        "My Winter Car VIN Decoder v{}",
+        env!("CARGO_PKG_VERSION")
+    ));
+    ui.add_space(6.0);
+    ui.label(
+        "Every car in My Winter Car carries a VIN that encodes its factory \
+         specification: where and when it was built, the version, drivetrain, \
+         colours and options. Each field is a fixed number of characters.",
+    );
+    ui.add_space(6.0);
+    ui.label("The game stores the VIN in its save file carparts.txt, usually at:");
+    ui.monospace("%USERPROFILE%\\AppData\\LocalLow\\Amistech\\My Winter Car\\carparts.txt");
+    ui.add_space(6.0);
+    for model in VEHICLE_MODELS {
+        ui.strong(format!("{} VIN layout", model.name));
+        egui::Grid::new(("about_layout", model.code))
+            .striped(true)
+            .show(ui, |ui| {
+                ui.strong("Pos");
+                ui.strong("Field");
+                ui.strong("Chars");
+                ui.end_row();
+                for (field, pos) in model.structure.iter().zip(field_positions(model.structure)) {
+                    ui.monospace(pos);
+                    let name = ui.label(field.display);
+                    if let Some(description) = field.description() {
+                        name.on_hover_text(description);
+                    }
+                    ui.label(field.len.to_string());
+                    ui.end_row();
+                }
+            });
     }
 }
 
 /// Show info for special VIN combinations
 fn show_info_labels(ui: &mut egui::Ui, v: &str, i: &str) {
-    if v == "G" && i == "M" {
+    let version = Version::from_code(v);
+    if version == Some(Version::Slx) && i == "M" {
         ui.vertical_centered(|ui| {
-            ui.label("SLX + Tachometer Package: Center console & Sport steering wheel ");
+            ui.add(
+                egui::Label::new(
+                    "SLX + Tachometer Package: Center console & Sport steering wheel ",
+                )
+                .wrap(),
+            );
         });
-    } else if v == "P" {
+    } else if version == Some(Version::Gt) {
         ui.vertical_centered(|ui| {
-            ui.label(
-                "GT Equipment: Sport steering wheel, Special gear stick & Quick steering ratio",
+            ui.add(
+                egui::Label::new(
+                    "GT Equipment: Sport steering wheel, Special gear stick & Quick steering ratio",
+                )
+                .wrap(),
             );
         });
     }
 }
 
-/// Render VIN decode table with given data source
-fn render_vin_table<'a>(
-    ui: &mut egui::Ui,
-    decode_map: &HashMap<&'static str, HashMap<&'static str, &'static str>>,
-    get_value: impl Fn(&str) -> &'a str,
-) {
+/// One-line drivetrain summary: layout, axle ratio and differential, or a
+/// note that the Drive code is not a known drivetrain
+fn drivetrain_note(decoded: &DecodedVin) -> Option<String> {
+    let code = decoded.value("Drive");
+    if code.is_empty() || code == "-" {
+        return None;
+    }
+    let Some(drive) = decoded.get::<Drive>() else {
+        return Some(format!(
+            "Unknown drive code \"{}\": every documented car is rear-wheel drive",
+            code
+        ));
+    };
+    let mut parts = vec![drive.layout().to_string()];
+    if let FieldStatus::Decoded(ratio) = decoded.status("AxleRatio") {
+        parts.push(format!("{} axle ratio", ratio));
+    }
+    if let FieldStatus::Decoded(lock) = decoded.status("AxleLock") {
+        parts.push(format!("{} rear differential", lock));
+    }
+    Some(format!("Drivetrain: {}", parts.join(", ")))
+}
+
+/// How long "Copied!" shows after copying the complete VIN, in seconds
+const COPIED_NOTE_SECS: f64 = 1.5;
+
+/// Widest the Decoded column grows before its text wraps
+const DECODED_COLUMN_WIDTH: f32 = 170.0;
+
+/// Below this width the results switch to a single-column list
+const NARROW_LAYOUT_WIDTH: f32 = 400.0;
+
+/// Results table column to sort by
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum SortColumn {
+    /// Field order of the VIN itself
+    #[default]
+    VinOrder,
+    Field,
+    Value,
+    Decoded,
+}
+
+/// Sort state of the results table
+#[derive(Clone, Copy, Debug, Default)]
+struct TableSort {
+    column: SortColumn,
+    descending: bool,
+}
+
+impl TableSort {
+    /// Sort by `column`, or flip the direction when it is already selected
+    fn toggle(&mut self, column: SortColumn) {
+        if self.column == column {
+            self.descending = !self.descending;
+        } else {
+            self.column = column;
+            self.descending = false;
+        }
+    }
+
+    /// Header text with a direction arrow for the active column
+    fn header(&self, column: SortColumn, title: &str) -> String {
+        match (self.column == column, self.descending) {
+            (true, false) => format!("{} ▲", title),
+            (true, true) => format!("{} ▼", title),
+            (false, _) => title.to_string(),
+        }
+    }
+}
+
+/// Fields of a decode as `(field, raw code, decoded label)` in the order selected by `sort`
+fn sorted_fields<'d, 'a>(
+    decoded: &'d DecodedVin<'a>,
+    sort: TableSort,
+) -> Vec<(&'a VinField, &'d str, &'static str)> {
+    let mut fields: Vec<_> = decoded
+        .fields()
+        .map(|(field, val, status)| (field, val, status.label()))
+        .collect();
+    match sort.column {
+        SortColumn::VinOrder => {}
+        SortColumn::Field => fields.sort_by_key(|(f, ..)| f.display),
+        SortColumn::Value => fields.sort_by_key(|(_, val, _)| *val),
+        SortColumn::Decoded => fields.sort_by_key(|(.., status)| *status),
+    }
+    if sort.descending {
+        fields.reverse();
+    }
+    fields
+}
+
+/// How the Serial's value is shown in the results
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+enum SerialDisplay {
+    /// Exactly as stored or typed
+    #[default]
+    AsEntered,
+    /// Thousands separators, e.g. "12,345"
+    Grouped,
+    /// Zero-padded to the field length, e.g. "01234"
+    Padded,
+}
+
+impl SerialDisplay {
+    const ALL: [SerialDisplay; 3] = [
+        SerialDisplay::AsEntered,
+        SerialDisplay::Grouped,
+        SerialDisplay::Padded,
+    ];
+
+    /// Name shown in the settings dropdown
+    fn name(self) -> &'static str {
+        match self {
+            SerialDisplay::AsEntered => "As entered",
+            SerialDisplay::Grouped => "12,345",
+            SerialDisplay::Padded => "01234",
+        }
+    }
+
+    /// `val` of `field` as shown; other fields and non-numeric serials are unchanged
+    fn format<'v>(self, field: &VinField, val: &'v str) -> Cow<'v, str> {
+        if field.key != "Serial" || val.is_empty() || !val.chars().all(|c| c.is_ascii_digit()) {
+            return Cow::Borrowed(val);
+        }
+        match self {
+            SerialDisplay::AsEntered => Cow::Borrowed(val),
+            SerialDisplay::Grouped => {
+                let digits = val.trim_start_matches('0');
+                let digits = if digits.is_empty() { "0" } else { digits };
+                let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+                for (i, c) in digits.chars().enumerate() {
+                    if i > 0 && (digits.len() - i) % 3 == 0 {
+                        out.push(',');
+                    }
+                    out.push(c);
+                }
+                Cow::Owned(out)
+            }
+            SerialDisplay::Padded => Cow::Owned(format!("{:0>1$}", val, field.len)),
+        }
+    }
+}
+
+/// Display options of the results table
+#[derive(Clone, Copy)]
+struct TableView<'p> {
+    /// Hide the raw Value column
+    decoded_only: bool,
+    serial_display: SerialDisplay,
+    /// Follow assembly plant names with their country
+    plant_locations: bool,
+    /// Lead each row with the field's character range
+    positions: bool,
+    /// Decode to highlight differences against
+    pinned: Option<&'p DecodedVin<'p>>,
+}
+
+/// Decoded label of a field, an assembly plant followed by its country from
+/// `vin_tables.toml` when `with_location` is set, e.g. "Rheine, West Germany"
+fn decoded_label<'s>(
+    field: &VinField,
+    val: &str,
+    status: &'s str,
+    with_location: bool,
+) -> Cow<'s, str> {
+    let location = config::get().plant_locations.get(val);
+    match location {
+        Some(country) if with_location && field.key == "AssemblyPlant" && !status.is_empty() => {
+            Cow::Owned(format!("{}, {}", status, country))
+        }
+        _ => Cow::Borrowed(status),
+    }
+}
+
+/// "did you mean" hint after an unmapped code that is one slip away from a known one
+fn show_suggestion(ui: &mut egui::Ui, decoded: &DecodedVin, field: &VinField) {
+    if let Some((code, label)) = decoded.suggestion(field.key) {
+        ui.colored_label(
+            ui.visuals().warn_fg_color,
+            format!("did you mean {} ({})?", code, label),
+        );
+    }
+}
+
+/// Character range of `field` within `structure`, e.g. "8-12"
+fn field_position(structure: &[VinField], field: &VinField) -> String {
+    structure
+        .iter()
+        .position(|f| f.key == field.key)
+        .and_then(|i| field_positions(structure).into_iter().nth(i))
+        .unwrap_or_default()
+}
+
+/// Highlight for fields that differ from the pinned VIN
+const PINNED_DIFF_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 200, 80);
+
+/// Pinned code of a field when it differs from `val`
+fn pinned_code<'p>(field: &VinField, val: &str, pinned: Option<&'p DecodedVin>) -> Option<&'p str> {
+    pinned
+        .map(|p| p.value(field.key))
+        .filter(|code| *code != val)
+}
+
+/// Field name text, highlighted when the field differs from the pinned VIN
+fn mark_pinned(
+    name: egui::RichText,
+    field: &VinField,
+    val: &str,
+    pinned: Option<&DecodedVin>,
+) -> egui::RichText {
+    match pinned_code(field, val, pinned) {
+        Some(_) => name.color(PINNED_DIFF_COLOR),
+        None => name,
+    }
+}
+
+/// Add the pinned code to the hover text of a field that differs from it
+fn pinned_difference(
+    response: egui::Response,
+    field: &VinField,
+    val: &str,
+    pinned: Option<&DecodedVin>,
+) -> egui::Response {
+    let Some(code) = pinned_code(field, val, pinned) else {
+        return response;
+    };
+    let status = pinned.map_or("", |p| p.status(field.key).label());
+    let pinned_text = if status.is_empty() {
+        format!("Pinned: {}", code)
+    } else {
+        format!("Pinned: {} — {}", code, status)
+    };
+    response.on_hover_text(pinned_text)
+}
+
+/// Three-column results grid for regular window widths
+fn render_vin_grid(ui: &mut egui::Ui, decoded: &DecodedVin, sort: &mut TableSort, view: TableView) {
+    let structure = decoded.structure();
+    let table_width = if view.decoded_only { 290.0 } else { 380.0 };
+    let table_width = if view.positions {
+        table_width + 60.0
+    } else {
+        table_width
+    };
     ui.horizontal(|ui| {
-        ui.add_space((ui.available_width() - 380.0) / 2.0);
-        egui::Frame::new()
+        ui.add_space((ui.available_width() - table_width) / 2.0);
+        panel_frame(ui)
             .inner_margin(10.0)
             .outer_margin(5.0)
-            .corner_radius(2.0)
-            .fill(egui::Color32::from_rgb(45, 45, 47))
-            .stroke(egui::Stroke::new(
-                3.0,
-                egui::Color32::from_rgb(100, 100, 105),
-            ))
             .show(ui, |ui| {
                 egui::Grid::new("vin_table")
                     .striped(true)
                     .spacing([10.0, 4.0])
                     .min_col_width(80.0)
                     .show(ui, |ui| {
-                        ui.strong("Field");
-                        ui.strong("Value");
-                        ui.strong("Decoded");
+                        if view.positions {
+                            ui.label(egui::RichText::new("Pos").strong())
+                                .on_hover_text("Character positions in the VIN");
+                        }
+                        for (column, title) in [
+                            (SortColumn::Field, "Field"),
+                            (SortColumn::Value, "Value"),
+                            (SortColumn::Decoded, "Decoded"),
+                        ] {
+                            if view.decoded_only && column == SortColumn::Value {
+                                continue;
+                            }
+                            let header = egui::RichText::new(sort.header(column, title)).strong();
+                            if ui
+                                .selectable_label(sort.column == column, header)
+                                .on_hover_text("Sort by this column")
+                                .clicked()
+                            {
+                                sort.toggle(column);
+                            }
+                        }
                         ui.end_row();
-                        for field in VIN_STRUCTURE {
-                            let val = get_value(field.key);
-                            let status = match decode_map.get(field.key).and_then(|m| m.get(val)) {
-                                Some(d) => d,
-                                None if val == "-" => "Standard / None",
-                                None if field.key != "Serial" && !val.is_empty() => {
-                                    "!! [UNKNOWN] !!"
-                                }
-                                _ => "",
+                        for (field, val, status) in sorted_fields(decoded, *sort) {
+                            let rgb =
+                                field_rgb(field.key, val, || Some(decoded.value("ColorsBody")));
+                            let menu = |response: egui::Response| {
+                                row_context_menu(&response, field, val, status, rgb)
                             };
-                            ui.label(field.display);
-                            ui.label(val);
-                            ui.horizontal(|ui| {
-                                ui.label(status);
+                            if view.positions {
+                                ui.monospace(field_position(structure, field));
+                            }
+                            menu(pinned_difference(
+                                ui.add(
+                                    egui::Label::new(mark_pinned(
+                                        field.display.into(),
+                                        field,
+                                        val,
+                                        view.pinned,
+                                    ))
+                                    .sense(egui::Sense::click()),
+                                )
+                                .on_hover_text(field_tooltip(structure, field)),
+                                field,
+                                val,
+                                view.pinned,
+                            ));
+                            let shown = view.serial_display.format(field, val);
+                            if !view.decoded_only {
+                                menu(ui.add(
+                                    egui::Label::new(shown.as_ref()).sense(egui::Sense::click()),
+                                ));
+                            }
+                            ui.horizontal_top(|ui| {
+                                ui.set_max_width(DECODED_COLUMN_WIDTH);
+                                let text = if view.decoded_only && status.is_empty() {
+                                    shown
+                                } else {
+                                    decoded_label(field, val, status, view.plant_locations)
+                                };
+                                menu(
+                                    ui.add(
+                                        egui::Label::new(text.as_ref())
+                                            .wrap()
+                                            .sense(egui::Sense::click()),
+                                    ),
+                                );
                                 render_color_swatch(ui, field.key, val, || {
-                                    Some(get_value("ColorsBody"))
+                                    Some(decoded.value("ColorsBody"))
                                 });
+                                show_suggestion(ui, decoded, field);
                             });
                             ui.end_row();
                         }
                     });
             });
     });
+}
 
-    ui.add_space(8.0);
-    let v_val = get_value("Version");
-    let i_val = get_value("InstrumentPanel");
-    show_info_labels(ui, v_val, i_val);
+/// Single-column results list for narrow windows, one "Field: Value — Decoded" line per field
+fn render_vin_list(ui: &mut egui::Ui, decoded: &DecodedVin, sort: TableSort, view: TableView) {
+    let structure = decoded.structure();
+    panel_frame(ui)
+        .inner_margin(8.0)
+        .outer_margin(2.0)
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width());
+            for (field, val, status) in sorted_fields(decoded, sort) {
+                let rgb = field_rgb(field.key, val, || Some(decoded.value("ColorsBody")));
+                let menu =
+                    |response: egui::Response| row_context_menu(&response, field, val, status, rgb);
+                ui.horizontal_wrapped(|ui| {
+                    if view.positions {
+                        ui.weak(format!("[{}]", field_position(structure, field)));
+                    }
+                    let name = egui::RichText::new(format!("{}:", field.display)).strong();
+                    let name = mark_pinned(name, field, val, view.pinned);
+                    menu(pinned_difference(
+                        ui.add(egui::Label::new(name).sense(egui::Sense::click()))
+                            .on_hover_text(field_tooltip(structure, field)),
+                        field,
+                        val,
+                        view.pinned,
+                    ));
+                    let label = decoded_label(field, val, status, view.plant_locations);
+                    let text = if status.is_empty() {
+                        view.serial_display.format(field, val).into_owned()
+                    } else if view.decoded_only {
+                        label.into_owned()
+                    } else {
+                        format!("{} — {}", val, label)
+                    };
+                    menu(ui.add(egui::Label::new(text).sense(egui::Sense::click())));
+                    render_color_swatch(ui, field.key, val, || Some(decoded.value("ColorsBody")));
+                    show_suggestion(ui, decoded, field);
+                });
+            }
+        });
+}
 
-    let complete_vin: String = VIN_STRUCTURE.iter().map(|f| get_value(f.key)).collect();
-    ui.separator();
-    ui.vertical_centered(|ui| {
-        ui.monospace(format!("Complete VIN: {}", complete_vin));
-    });
+/// One row of a VIN comparison
+struct CompareRow<'a> {
+    field: &'a VinField,
+    /// Raw code and decoded label of each VIN, empty when its layout lacks the field
+    sides: [(&'a str, &'static str); 2],
 }
 
-/// VIN Decoder application state
-struct VinApp {
-    vin_input: String,
-    entries: Option<HashMap<String, String>>,
-    vin_error: Option<String>,
-    file_path: String,
-    vingen4_entries: Option<Vec<(String, String)>>,
-    last_source: LastSource,
-    decode_map: HashMap<&'static str, HashMap<&'static str, &'static str>>,
-    file_error: Option<String>,
+impl CompareRow<'_> {
+    /// Whether the two VINs have different codes for the field
+    fn differs(&self) -> bool {
+        self.sides[0].0 != self.sides[1].0
+    }
 }
 
-impl VinApp {
-    /// Get default carparts.txt path
-    fn default_file_path() -> String {
-        if let Ok(userprofile) = std::env::var("USERPROFILE") {
-            format!(
-                "{}\\AppData\\LocalLow\\Amistech\\My Winter Car\\carparts.txt",
-                userprofile
+/// Fields of `a` in VIN order, then those only `b`'s layout has, with both codes
+fn compare_rows<'a>(a: &'a DecodedVin, b: &'a DecodedVin) -> Vec<CompareRow<'a>> {
+    let side = |decoded: &'a DecodedVin, key| (decoded.value(key), decoded.status(key).label());
+    let extra = b
+        .structure()
+        .iter()
+        .filter(|f| !a.structure().iter().any(|other| other.key == f.key));
+    a.structure()
+        .iter()
+        .chain(extra)
+        .map(|field| CompareRow {
+            field,
+            sides: [side(a, field.key), side(b, field.key)],
+        })
+        .collect()
+}
+
+/// Two decoded VINs in one grid, rows that differ on the accent background
+fn render_vin_compare(ui: &mut egui::Ui, a: &DecodedVin, b: &DecodedVin) {
+    let rows = compare_rows(a, b);
+    // Row 0 is the header
+    let differs: Vec<bool> = std::iter::once(false)
+        .chain(rows.iter().map(CompareRow::differs))
+        .collect();
+    let changed = differs.iter().filter(|d| **d).count();
+    egui::Grid::new("vin_compare")
+        .num_columns(5)
+        .spacing([10.0, 4.0])
+        .with_row_color(move |row, style| {
+            differs
+                .get(row)
+                .copied()
+                .unwrap_or(false)
+                .then(|| style.visuals.selection.bg_fill.gamma_multiply(0.6))
+        })
+        .show(ui, |ui| {
+            for title in ["Field", "First", "Decoded", "Second", "Decoded"] {
+                ui.strong(title);
+            }
+            ui.end_row();
+            for row in &rows {
+                ui.label(row.field.display);
+                for (val, label) in row.sides {
+                    ui.monospace(val);
+                    ui.label(label);
+                }
+                ui.end_row();
+            }
+        });
+    ui.weak(match changed {
+        0 => "The VINs are identical".to_string(),
+        1 => "1 field differs".to_string(),
+        n => format!("{} fields differ", n),
+    });
+}
+
+/// Render an already decoded VIN, returns the area it covers
+fn render_vin_table(
+    ui: &mut egui::Ui,
+    decoded: &DecodedVin,
+    sort: &mut TableSort,
+    view: TableView,
+) -> egui::Rect {
+    ui.vertical(|ui| {
+        if sort.column != SortColumn::VinOrder {
+            ui.vertical_centered(|ui| {
+                if ui.small_button("↺ Reset to VIN order").clicked() {
+                    *sort = TableSort::default();
+                }
+            });
+        }
+        if ui.available_width() < NARROW_LAYOUT_WIDTH {
+            render_vin_list(ui, decoded, *sort, view);
+        } else {
+            render_vin_grid(ui, decoded, sort, view);
+        }
+
+        ui.add_space(8.0);
+        let v_val = decoded.value("Version");
+        let i_val = decoded.value("InstrumentPanel");
+        show_info_labels(ui, v_val, i_val);
+        if let Some(note) = drivetrain_note(decoded) {
+            ui.vertical_centered(|ui| {
+                ui.add(egui::Label::new(note).wrap());
+            });
+        }
+
+        for warning in check_combinations(|key| decoded.value(key)) {
+            ui.vertical_centered(|ui| {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!("⚠ Implausible combination: {}", warning),
+                );
+            });
+        }
+
+        let complete_vin = decoded.complete_vin();
+        ui.separator();
+        ui.vertical_centered(|ui| {
+            let confidence = decode_confidence(decoded);
+            let color = match confidence {
+                90.. => ok_color(ui.visuals()),
+                60..=89 => ui.visuals().warn_fg_color,
+                _ => ui.visuals().error_fg_color,
+            };
+            ui.label(
+                egui::RichText::new(format!("Decode confidence: {}%", confidence))
+                    .strong()
+                    .color(color),
             )
+            .on_hover_text(format!(
+                "100% when every field decodes and no combination is implausible; \
+                 -{} per field that doesn't decode, -{} per implausible combination",
+                UNKNOWN_FIELD_PENALTY, COMBO_RULE_PENALTY
+            ));
+            ui.horizontal(|ui| {
+                ui.monospace(format!("Complete VIN: {}", complete_vin));
+                // Per table, so only the clicked side of the split view confirms
+                let copied_id = ui.id().with(("complete_vin_copied_at", &complete_vin));
+                let now = ui.input(|i| i.time);
+                if ui
+                    .small_button("📋")
+                    .on_hover_text("Copy the complete VIN")
+                    .clicked()
+                {
+                    ui.ctx().copy_text(complete_vin.clone());
+                    ui.data_mut(|d| d.insert_temp(copied_id, now));
+                }
+                let copied_at = ui.data(|d| d.get_temp::<f64>(copied_id));
+                if let Some(elapsed) = copied_at.map(|t| now - t).filter(|e| *e < COPIED_NOTE_SECS)
+                {
+                    ui.weak("Copied!");
+                    ui.ctx()
+                        .request_repaint_after(std::time::Duration::from_secs_f64(
+                            COPIED_NOTE_SECS - elapsed,
+                        ));
+                }
+            });
+            let code = share_code(&complete_vin);
+            ui.horizontal(|ui| {
+                ui.monospace(format!("Share code: {}", code));
+                if ui
+                    .small_button("📋")
+                    .on_hover_text(
+                        "Copy the share code: a short ID of this VIN, not a game checksum",
+                    )
+                    .clicked()
+                {
+                    ui.ctx().copy_text(code.clone());
+                }
+                if let Some(link) = deep_link(&complete_vin) {
+                    if ui
+                        .small_button("🔗")
+                        .on_hover_text(format!("Copy a link that decodes this VIN: {}", link))
+                        .clicked()
+                    {
+                        ui.ctx().copy_text(link);
+                    }
+                }
+            });
+        });
+    })
+    .response
+    .rect
+}
+
+/// Expand `%VAR%`, `$VAR` and `${VAR}` references; unset variables are kept literally
+fn expand_env_vars(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    let mut rest = path;
+    while let Some(start) = rest.find(['%', '$']) {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        let (name, len) = if let Some(inner) = tail.strip_prefix('%') {
+            match inner.find('%') {
+                Some(end) => (&inner[..end], end + 2),
+                None => ("", 1),
+            }
+        } else if let Some(inner) = tail.strip_prefix("${") {
+            match inner.find('}') {
+                Some(end) => (&inner[..end], end + 3),
+                None => ("", 1),
+            }
         } else {
-            String::new()
+            let end = tail[1..]
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(tail.len() - 1);
+            (&tail[1..1 + end], end + 1)
+        };
+        match std::env::var(name) {
+            Ok(value) if !name.is_empty() => out.push_str(&value),
+            _ => out.push_str(&tail[..len]),
+        }
+        rest = &tail[len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `path` with `expand_env_vars` applied; paths that aren't valid UTF-8 are
+/// kept byte for byte, they can't be expanded without mangling them
+fn expand_path(path: &Path) -> PathBuf {
+    match path.to_str() {
+        Some(s) => PathBuf::from(expand_env_vars(s)),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Month names, indexed by month number - 1
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Month codes in calendar order with "01 January" style labels, from the
+/// inverted Month table (its codes aren't in alphabetical order)
+fn month_options(decode_map: &DecodeMap) -> Vec<(&'static str, String)> {
+    MONTH_NAMES
+        .iter()
+        .enumerate()
+        .filter_map(|(i, name)| {
+            let number = format!("{:02}", i + 1);
+            encode_field(decode_map, "Month", &number)
+                .map(|code| (code, format!("{} {}", number, name)))
+        })
+        .collect()
+}
+
+/// Dropdown that shows `choices` labels and stores the chosen code in `value`
+fn builder_combo(
+    ui: &mut egui::Ui,
+    id: &str,
+    value: &mut String,
+    choices: &[(&'static str, String)],
+) {
+    let selected = choices
+        .iter()
+        .find(|(code, _)| *code == value.as_str())
+        .map_or(value.as_str(), |(_, label)| label.as_str())
+        .to_string();
+    egui::ComboBox::from_id_salt(("builder", id))
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            for (code, label) in choices {
+                if ui.selectable_label(value == code, label).clicked() {
+                    *value = code.to_string();
+                }
+            }
+        });
+}
+
+/// Dropdown label of the `index`th vehicle, e.g. "Vehicle 2 (Rivett)"
+fn vehicle_label(index: usize, entries: &[(String, String)]) -> String {
+    let model = entries
+        .iter()
+        .find(|(k, _)| k == "Model")
+        .and_then(|(_, v)| model_index_for_code(&normalize_code(unwrap_typed_value(v).1, false)));
+    match model {
+        Some(i) => format!("Vehicle {} ({})", index + 1, VEHICLE_MODELS[i].name),
+        None => format!("Vehicle {}", index + 1),
+    }
+}
+
+/// Keyboard binding with its cheat-sheet description
+struct Shortcut {
+    keys: egui::KeyboardShortcut,
+    description: &'static str,
+}
+
+impl Shortcut {
+    const fn new(modifiers: egui::Modifiers, key: egui::Key, description: &'static str) -> Self {
+        Self {
+            keys: egui::KeyboardShortcut::new(modifiers, key),
+            description,
+        }
+    }
+
+    /// Consume the binding from this frame's input, `true` if it was pressed
+    fn consume(&self, ctx: &egui::Context) -> bool {
+        ctx.input_mut(|i| i.consume_shortcut(&self.keys))
+    }
+}
+
+const SHORTCUT_REPEAT_EXPORT: Shortcut = Shortcut::new(
+    egui::Modifiers::COMMAND,
+    egui::Key::S,
+    "Repeat the last export",
+);
+const SHORTCUT_UNDO: Shortcut =
+    Shortcut::new(egui::Modifiers::COMMAND, egui::Key::Z, "Undo Clear/Reset");
+const SHORTCUT_DECODE: Shortcut = Shortcut::new(
+    egui::Modifiers::NONE,
+    egui::Key::Enter,
+    "Decode the VIN input / next wizard step",
+);
+const SHORTCUT_HISTORY_BACK: Shortcut = Shortcut::new(
+    egui::Modifiers::NONE,
+    egui::Key::ArrowUp,
+    "Recall an earlier VIN (in the VIN input)",
+);
+const SHORTCUT_HISTORY_FORWARD: Shortcut = Shortcut::new(
+    egui::Modifiers::NONE,
+    egui::Key::ArrowDown,
+    "Recall a later VIN (in the VIN input)",
+);
+const SHORTCUT_CHEAT_SHEET: Shortcut = Shortcut::new(
+    egui::Modifiers::NONE,
+    egui::Key::Questionmark,
+    "Show or hide this list",
+);
+
+/// Every binding, in cheat-sheet order
+const SHORTCUTS: [&Shortcut; 6] = [
+    &SHORTCUT_DECODE,
+    &SHORTCUT_HISTORY_BACK,
+    &SHORTCUT_HISTORY_FORWARD,
+    &SHORTCUT_REPEAT_EXPORT,
+    &SHORTCUT_UNDO,
+    &SHORTCUT_CHEAT_SHEET,
+];
+
+/// Number of decoded VINs kept for Up/Down recall
+const HISTORY_LEN: usize = 50;
+
+/// Shown instead of loading when there is no path, e.g. when `USERPROFILE`
+/// isn't set and no default save location could be determined
+const NO_PATH_HINT: &str = "No save location found. Use Browse... to pick carparts.txt";
+
+/// Seconds between modification-time checks of a watched file
+const WATCH_INTERVAL: f64 = 1.0;
+
+/// Polling watcher that reloads carparts.txt when the game saves
+struct FileWatch {
+    /// Expanded path being watched
+    path: PathBuf,
+    /// Modification time seen at the last check
+    modified: Option<std::time::SystemTime>,
+    /// `egui` time of the last check
+    last_check: f64,
+}
+
+/// Most field changes kept in the watch-mode change log
+const CHANGE_LOG_LEN: usize = 30;
+
+/// A field whose decode changed between two loads of the watched file
+struct FieldChange {
+    /// Display name of the field
+    field: &'static str,
+    /// Code and decoded label before and after, e.g. "G (SLX)"
+    old: String,
+    new: String,
+    /// Wall-clock time of the reload
+    at: std::time::SystemTime,
+}
+
+/// Fields of `structure` that changed between `old` and `new` values, with
+/// their decoded labels
+fn field_changes<'a>(
+    structure: &[VinField],
+    decode_map: &DecodeMap,
+    old: impl Fn(&str) -> &'a str,
+    new: impl Fn(&str) -> &'a str,
+    at: std::time::SystemTime,
+) -> Vec<FieldChange> {
+    let describe = |field: &VinField, code: &str| match field_status(decode_map, field, code) {
+        "" => code.to_string(),
+        label => format!("{} ({})", code, label),
+    };
+    structure
+        .iter()
+        .filter(|field| old(field.key) != new(field.key))
+        .map(|field| FieldChange {
+            field: field.display,
+            old: describe(field, old(field.key)),
+            new: describe(field, new(field.key)),
+            at,
+        })
+        .collect()
+}
+
+/// UTC time of day as "HH:MM:SS"
+fn clock_time(at: std::time::SystemTime) -> String {
+    let secs = at
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+        % 86_400;
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Progress of the step-by-step entry, one field at a time
+#[derive(Default)]
+struct VinWizard {
+    /// Codes of the fields entered so far
+    codes: Vec<String>,
+    /// Text typed for the current field
+    input: String,
+    /// Problem with the last entered code
+    error: Option<String>,
+}
+
+/// VIN Decoder application state
+struct VinApp {
+    vin_input: String,
+    entries: Option<HashMap<String, String>>,
+    vin_error: Option<String>,
+    /// Alignment guesses for a VIN that is one character off
+    alignments: Option<AlignmentHints>,
+    /// carparts.txt path as chosen, kept as an `OsString` so paths that aren't
+    /// valid UTF-8 still reopen
+    file_path: PathBuf,
+    /// Text of the path field: `file_path` shown lossily, edits replace `file_path`
+    file_path_text: String,
+    vingen4_entries: Option<Vec<(String, String)>>,
+    /// Every vehicle of the loaded file; `vingen4_entries` is the selected one
+    vehicles: Vehicles,
+    /// Index into `vehicles` of the vehicle being decoded
+    vehicle_index: usize,
+    last_source: LastSource,
+    /// Decode tables per entry of `VEHICLE_MODELS`
+    decode_maps: Vec<DecodeMap>,
+    /// Manually chosen model (index into `VEHICLE_MODELS`), `None` to detect
+    model_override: Option<usize>,
+    file_error: Option<String>,
+    /// Format used by the Ctrl+S shortcut
+    last_export: ExportFormat,
+    /// Result of the last export (written path or error)
+    export_status: Option<String>,
+    /// Result of the last blank template save
+    template_status: Option<String>,
+    /// Screen area of the rendered results, for screenshots
+    results_rect: Option<egui::Rect>,
+    /// Screenshot requested and waiting for the frame capture
+    pending_screenshot: Option<ScreenshotTarget>,
+    /// Hide the serial in exports and screenshots
+    redact_serial: bool,
+    /// Gentle note under the VIN input (not an error)
+    vin_hint: Option<&'static str>,
+    /// Last checked file path and whether it existed
+    path_check: (PathBuf, bool),
+    /// `file_path` with environment variables expanded, updated with `path_check`
+    expanded_path: PathBuf,
+    /// About/help window visible
+    show_about: bool,
+    /// Keyboard shortcut cheat sheet visible
+    show_shortcuts: bool,
+    /// Most recent file error, kept after later successful loads
+    last_error: Option<String>,
+    /// Diagnostics of the most recent file load
+    diagnostics: Option<ParseDiagnostics>,
+    /// Pasted hex dump of a VINGen4 section
+    hex_input: String,
+    /// Sort order of the results table
+    table_sort: TableSort,
+    /// State before the last Clear/Reset
+    undo: Option<UndoState>,
+    /// Codes chosen in the reverse builder, keyed by field key
+    builder: HashMap<&'static str, String>,
+    /// Text of the option label search
+    option_search: String,
+    /// Hide the raw Value column for a readable spec sheet
+    decoded_only: bool,
+    /// How the Serial's value is shown in the results
+    serial_display: SerialDisplay,
+    /// Follow assembly plant names with their country
+    plant_locations: bool,
+    /// Show each field's character range in the results
+    show_positions: bool,
+    /// Show the file and typed decodes side by side when both exist
+    split_view: bool,
+    /// The background load in flight is the startup load of the default path
+    auto_loading: bool,
+    /// The loaded file came from the startup load
+    auto_loaded: bool,
+    /// Result channel of the background load in flight, with the path it read
+    load_rx: Option<std::sync::mpsc::Receiver<(PathBuf, LoadResult)>>,
+    /// File dropped while a load was in flight, loaded once it finishes
+    queued_drop: Option<egui::DroppedFile>,
+    /// Step-by-step VIN entry
+    wizard: VinWizard,
+    /// The two VINs typed into "Compare two VINs"
+    compare_inputs: [String; 2],
+    /// Match codes with their exact case instead of uppercasing input first
+    case_sensitive_codes: bool,
+    /// Active file watch, reloading on every save
+    watch: Option<FileWatch>,
+    /// Fields changed by watched reloads, newest first
+    change_log: Vec<FieldChange>,
+    /// Free-text note attached to exports of the shown decode
+    notes: String,
+    /// Decode pinned as a baseline to compare later decodes against
+    pinned: Option<DecodedVin<'static>>,
+    /// Decoded VINs this session, oldest first
+    history: Vec<String>,
+    /// History entry shown in the input while recalling
+    history_pos: Option<usize>,
+    /// Scale factor the style was last applied for
+    pixels_per_point: Option<f32>,
+    /// Colour scheme, toggled at the top of the window
+    theme: Theme,
+    /// carparts.txt last loaded successfully, remembered for the next run
+    loaded_path: Option<PathBuf>,
+    /// Show unknown 4-byte values as u32/i32/f32 under Format details
+    show_number_readings: bool,
+    /// Index into the loaded file's tags picked in the section inspector,
+    /// with its parsed entries
+    inspected_section: Option<(usize, SectionEntries)>,
+    /// List the inspected section's entries by key instead of file order
+    sort_section_by_key: bool,
+    /// Decimal places of float values in the results and the section inspector
+    float_decimals: usize,
+    /// Input of the last "Validate against loaded file" and its mismatches
+    validation: Option<(String, Result<Vec<FieldMismatch>, String>)>,
+}
+
+/// Field whose code differs between the loaded file and the typed VIN
+struct FieldMismatch {
+    field: &'static str,
+    file: String,
+    typed: String,
+}
+
+/// Fields of `structure` whose `file` and `typed` codes differ
+fn field_mismatches<'a>(
+    structure: &'static [VinField],
+    file: impl Fn(&str) -> &'a str,
+    typed: impl Fn(&str) -> &'a str,
+) -> Vec<FieldMismatch> {
+    structure
+        .iter()
+        .filter(|field| file(field.key) != typed(field.key))
+        .map(|field| FieldMismatch {
+            field: field.display,
+            file: file(field.key).to_string(),
+            typed: typed(field.key).to_string(),
+        })
+        .collect()
+}
+
+/// How long the Undo offer stays visible after Clear/Reset, in seconds
+const UNDO_SECONDS: f64 = 8.0;
+
+/// Input and results saved by Clear/Reset for a single undo step
+struct UndoState {
+    vin_input: String,
+    entries: Option<HashMap<String, String>>,
+    file_path: PathBuf,
+    vingen4_entries: Option<Vec<(String, String)>>,
+    vehicles: Vehicles,
+    vehicle_index: usize,
+    last_source: LastSource,
+    /// `egui` time the state was saved, for expiring the offer
+    saved_at: f64,
+    /// What was undone, e.g. "Cleared"
+    action: &'static str,
+}
+
+/// Where a captured results image goes
+enum ScreenshotTarget {
+    Clipboard,
+    File,
+    /// PNG of an export bundle, after the files already `written`
+    Bundle {
+        png: PathBuf,
+        written: Vec<PathBuf>,
+    },
+}
+
+impl VinApp {
+    /// Get default carparts.txt path
+    fn default_file_path() -> PathBuf {
+        match std::env::var_os("USERPROFILE") {
+            Some(userprofile) => {
+                let mut path = PathBuf::from(userprofile);
+                path.push("AppData\\LocalLow\\Amistech\\My Winter Car\\carparts.txt");
+                path
+            }
+            None => PathBuf::new(),
+        }
+    }
+
+    /// Use `path` for loading, showing it lossily in the path field
+    fn set_file_path(&mut self, path: PathBuf) {
+        self.file_path_text = path.to_string_lossy().into_owned();
+        self.file_path = path;
+    }
+
+    /// Field values of the currently shown decode, keyed by field key
+    fn current_values(&self) -> Option<HashMap<&str, Cow<'_, str>>> {
+        match self.last_source {
+            LastSource::File => self.file_values(),
+            LastSource::Vin => self.typed_values(),
+            LastSource::None => None,
+        }
+    }
+
+    /// Field values of the decoded VIN input
+    fn typed_values(&self) -> Option<HashMap<&str, Cow<'_, str>>> {
+        self.entries.as_ref().map(|entries| {
+            entries
+                .iter()
+                .map(|(k, v)| (&**k, Cow::Borrowed(&**v)))
+                .collect()
+        })
+    }
+
+    /// Field values of the loaded file, type tags stripped
+    fn file_values(&self) -> Option<HashMap<&str, Cow<'_, str>>> {
+        self.vingen4_entries
+            .as_ref()
+            .map(|entries| self.entry_values(entries))
+    }
+
+    /// Field values of loaded entries, type tags stripped
+    fn entry_values<'e>(&self, entries: &'e [(String, String)]) -> HashMap<&'e str, Cow<'e, str>> {
+        entries
+            .iter()
+            .map(|(k, v)| match unwrap_typed_value(v) {
+                (Some("float"), code) => {
+                    let rounded = format_float(code, self.float_decimals);
+                    (&**k, rounded.map_or(Cow::Borrowed(code), Cow::Owned))
+                }
+                (_, code) => (&**k, normalize_code(code, self.case_sensitive_codes)),
+            })
+            .collect()
+    }
+
+    /// Log the fields a watched reload changed, keeping the newest `CHANGE_LOG_LEN`
+    fn log_changes(&mut self, entries: &[(String, String)]) {
+        let Some(ref previous) = self.vingen4_entries else {
+            return;
+        };
+        let old = self.entry_values(previous);
+        let new = self.entry_values(entries);
+        let old_value = |key: &str| old.get(key).map_or(EMPTY, |v| v.as_ref());
+        let new_value = |key: &str| new.get(key).map_or(EMPTY, |v| v.as_ref());
+        let (model, _) = self.resolve_model(new_value("Model"));
+        let changes = field_changes(
+            VEHICLE_MODELS[model].structure,
+            &self.decode_maps[model],
+            old_value,
+            new_value,
+            std::time::SystemTime::now(),
+        );
+        self.change_log.splice(0..0, changes.into_iter().rev());
+        self.change_log.truncate(CHANGE_LOG_LEN);
+    }
+
+    /// Remember the current input and results so `action` can be undone
+    fn save_undo(&mut self, action: &'static str, now: f64) {
+        self.undo = Some(UndoState {
+            vin_input: self.vin_input.clone(),
+            entries: self.entries.clone(),
+            file_path: self.file_path.clone(),
+            vingen4_entries: self.vingen4_entries.clone(),
+            vehicles: self.vehicles.clone(),
+            vehicle_index: self.vehicle_index,
+            last_source: self.last_source,
+            saved_at: now,
+            action,
+        });
+    }
+
+    /// Empty the VIN input and drop all results
+    fn clear(&mut self, now: f64) {
+        self.save_undo("Cleared", now);
+        self.vin_input.clear();
+        self.entries = None;
+        self.vingen4_entries = None;
+        self.vehicles.clear();
+        self.vehicle_index = 0;
+        self.last_source = LastSource::None;
+        self.vin_error = None;
+        self.alignments = None;
+        self.vin_hint = None;
+    }
+
+    /// Restore the state saved by the last Clear/Reset
+    fn undo(&mut self) {
+        if let Some(state) = self.undo.take() {
+            self.vin_input = state.vin_input;
+            self.entries = state.entries;
+            self.set_file_path(state.file_path);
+            self.vingen4_entries = state.vingen4_entries;
+            self.vehicles = state.vehicles;
+            self.vehicle_index = state.vehicle_index;
+            self.last_source = state.last_source;
+        }
+    }
+
+    /// Split the VIN input into field values; `Ok(None)` when it is empty
+    fn parse_input(&self) -> Result<Option<ParsedInput>, String> {
+        parse_vin_input(&self.vin_input, self.case_sensitive_codes, |code| {
+            self.resolve_model(code).0
+        })
+    }
+
+    /// Alignment guesses for undelimited input that is one character off,
+    /// with the index of the one decoding the most fields (earliest on ties)
+    fn alignment_hints(&self) -> Option<AlignmentHints> {
+        let input = normalize_code(&self.vin_input, self.case_sensitive_codes);
+        if input.contains('|') {
+            return None;
+        }
+        let vin = vin_tokens(&input).concat();
+        let (model, _) = self.resolve_model(model_code_in_vin(&vin));
+        let structure = VEHICLE_MODELS[model].structure;
+        let list = alignments(&vin, structure, &self.decode_maps[model]);
+        let best = (0..list.len()).max_by_key(|&i| (list[i].score, std::cmp::Reverse(i)))?;
+        Some(AlignmentHints {
+            list,
+            best,
+            total: structure.len(),
+        })
+    }
+
+    /// Suggested fixes for a VIN that is one character off; clicking one decodes it
+    fn render_alignments(&mut self, ui: &mut egui::Ui) {
+        let Some(AlignmentHints {
+            ref list,
+            best,
+            total,
+        }) = self.alignments
+        else {
+            return;
+        };
+        let mut chosen = None;
+        ui.vertical_centered(|ui| {
+            let guess = &list[best];
+            ui.horizontal(|ui| {
+                ui.label("Did you mean");
+                if ui
+                    .button(egui::RichText::new(&guess.vin).monospace())
+                    .on_hover_text(&guess.fields)
+                    .clicked()
+                {
+                    chosen = Some(guess.vin.clone());
+                }
+                ui.label(format!(
+                    "? ({}, {}/{} fields decode)",
+                    guess.edit, guess.score, total
+                ));
+            });
+        });
+        ui.collapsing("All alignments", |ui| {
+            egui::Grid::new("alignments_grid")
+                .striped(true)
+                .show(ui, |ui| {
+                    for (i, alignment) in list.iter().enumerate() {
+                        let fields = egui::RichText::new(&alignment.fields).monospace();
+                        let fields = if i == best { fields.strong() } else { fields };
+                        if ui
+                            .add(egui::Button::new(fields).frame(false))
+                            .on_hover_text("Decode this alignment")
+                            .clicked()
+                        {
+                            chosen = Some(alignment.vin.clone());
+                        }
+                        ui.label(&alignment.edit);
+                        ui.label(format!("{} decode", alignment.score));
+                        ui.end_row();
+                    }
+                });
+        });
+        if let Some(vin) = chosen {
+            self.vin_input = vin;
+            self.decode_input();
+        }
+    }
+
+    /// The input as it will be decoded (trimmed, uppercased, separators
+    /// removed), when that differs from what was typed
+    fn input_preview(&self) -> Option<String> {
+        let parsed = self.parse_input().ok()??;
+        let vin: String = parsed
+            .structure
+            .iter()
+            .map(|f| parsed.entries.get(f.key).map_or(EMPTY, String::as_str))
+            .collect();
+        (vin != self.vin_input).then_some(vin)
+    }
+
+    /// Decode the manually entered VIN
+    fn decode_input(&mut self) {
+        self.vin_hint = None;
+        match self.parse_input() {
+            // Accidental click: hint instead of replacing the results
+            Ok(None) => self.vin_hint = Some("Enter a VIN first"),
+            Ok(Some(parsed)) => {
+                self.entries = Some(parsed.entries);
+                self.vin_error = None;
+                self.alignments = None;
+                self.remember(parsed.vin);
+            }
+            Err(e) => {
+                self.vin_error = Some(e);
+                self.entries = None;
+                self.alignments = self.alignment_hints();
+            }
+        }
+        if self.vin_hint.is_none() {
+            self.last_source = LastSource::Vin;
+            self.undo = None;
+        }
+    }
+
+    /// Compare the typed VIN field by field with the loaded file
+    fn validate_against_file(&mut self) {
+        let result = match self.parse_input() {
+            Ok(None) => Err("Enter a VIN first".to_string()),
+            Ok(Some(parsed)) => match self.file_values() {
+                Some(file) => Ok(field_mismatches(
+                    parsed.structure,
+                    |key| file.get(key).map_or(EMPTY, |v| v.as_ref()),
+                    |key| parsed.entries.get(key).map_or(EMPTY, String::as_str),
+                )),
+                None => Err("No file loaded".to_string()),
+            },
+            Err(e) => Err(e),
+        };
+        self.validation = Some((self.vin_input.clone(), result));
+    }
+
+    /// Find a VIN inside the pasted text (forum post, chat message) and decode it
+    fn scan_input(&mut self) {
+        let text = normalize_code(&self.vin_input, self.case_sensitive_codes).into_owned();
+        let found = VEHICLE_MODELS
+            .iter()
+            .zip(&self.decode_maps)
+            .filter_map(|(model, map)| find_vin_in_text(&text, model.structure, map))
+            .max_by_key(|(_, score)| *score);
+        match found {
+            Some((vin, _)) => {
+                self.vin_input = vin;
+                self.decode_input();
+            }
+            None => {
+                self.vin_error = Some("No VIN found in the text".to_string());
+                self.alignments = None;
+                self.entries = None;
+                self.last_source = LastSource::Vin;
+            }
+        }
+    }
+
+    /// Add a decoded VIN to the history, moving repeats to the end
+    fn remember(&mut self, vin: String) {
+        self.history.retain(|v| *v != vin);
+        self.history.push(vin);
+        if self.history.len() > HISTORY_LEN {
+            self.history.remove(0);
+        }
+        self.history_pos = None;
+    }
+
+    /// Up/Down recall of earlier VINs while the input is empty or shows a
+    /// history entry; while editing other text the keys move the cursor as usual
+    fn recall_history(&mut self, ui: &egui::Ui, input_id: egui::Id) {
+        if self.history.is_empty() || !ui.memory(|m| m.has_focus(input_id)) {
+            return;
+        }
+        let shown = self.history.iter().position(|v| *v == self.vin_input);
+        if !self.vin_input.is_empty() && shown.is_none() {
+            return;
+        }
+        let pos = self.history_pos.filter(|_| shown.is_some()).or(shown);
+        let up = SHORTCUT_HISTORY_BACK.consume(ui.ctx());
+        let down = SHORTCUT_HISTORY_FORWARD.consume(ui.ctx());
+        let new_pos = match (up, down, pos) {
+            (true, _, None) => Some(self.history.len() - 1),
+            (true, _, Some(p)) => Some(p.saturating_sub(1)),
+            (false, true, Some(p)) if p + 1 < self.history.len() => Some(p + 1),
+            // Past the newest entry: back to an empty input
+            (false, true, Some(_)) => None,
+            _ => return,
+        };
+        self.history_pos = new_pos;
+        self.vin_input = new_pos.map_or_else(String::new, |p| self.history[p].clone());
+        if let Some(mut state) = egui::TextEdit::load_state(ui.ctx(), input_id) {
+            let end = egui::text::CCursor::new(self.vin_input.chars().count());
+            state
+                .cursor
+                .set_char_range(Some(egui::text::CCursorRange::one(end)));
+            state.store(ui.ctx(), input_id);
+        }
+    }
+
+    /// Reverse builder: pick every field from its known options and assemble the VIN
+    fn render_builder(&mut self, ui: &mut egui::Ui) {
+        let model = self.model_override.unwrap_or_else(|| {
+            let code = self.builder.get("Model").map_or("", String::as_str);
+            model_index_for_code(code).unwrap_or(0)
+        });
+        let structure = VEHICLE_MODELS[model].structure;
+        let decode_map = &self.decode_maps[model];
+        let options = all_field_options(structure, decode_map);
+        let months = month_options(decode_map);
+        egui::Grid::new("vin_builder")
+            .num_columns(2)
+            .spacing([10.0, 4.0])
+            .show(ui, |ui| {
+                for (field, (_, codes)) in structure.iter().zip(options) {
+                    let value = self.builder.entry(field.key).or_insert_with(|| {
+                        codes
+                            .first()
+                            .map_or_else(|| "0".repeat(field.len), |(code, _)| code.to_string())
+                    });
+                    ui.label(field.display);
+                    if field.key == "Month" && !months.is_empty() {
+                        builder_combo(ui, field.key, value, &months);
+                    } else if codes.is_empty() {
+                        ui.add(
+                            egui::TextEdit::singleline(value)
+                                .char_limit(field.len)
+                                .desired_width(60.0)
+                                .font(egui::TextStyle::Monospace),
+                        );
+                    } else {
+                        let choices: Vec<(&str, String)> = codes
+                            .iter()
+                            .map(|(code, label)| (*code, format!("{} — {}", code, label)))
+                            .collect();
+                        builder_combo(ui, field.key, value, &choices);
+                    }
+                    ui.end_row();
+                }
+            });
+        let built = structure
+            .iter()
+            .fold(
+                DecodedVin::builder(structure, decode_map).strict(false),
+                |b, f| b.set(f.key, self.builder.get(f.key).map_or("", String::as_str)),
+            )
+            .build_vin();
+        ui.horizontal(|ui| {
+            if ui
+                .button("Save blank template...")
+                .on_hover_text("Save every field with its options as a fillable Markdown sheet")
+                .clicked()
+            {
+                let template = export::render_template();
+                match save::save_text_with_dialog("Markdown", "md", "vin_template.md", &template) {
+                    Ok(Some(path)) => {
+                        self.template_status = Some(format!("Saved {}", path.display()))
+                    }
+                    Ok(None) => {}
+                    Err(e) => self.template_status = Some(e),
+                }
+            }
+            if let Some(ref status) = self.template_status {
+                ui.small(status);
+            }
+        });
+        match built {
+            Ok(vin) => {
+                ui.horizontal(|ui| {
+                    ui.monospace(&vin);
+                    if ui
+                        .button("Use")
+                        .on_hover_text("Copy the built VIN into the input and decode it")
+                        .clicked()
+                    {
+                        self.vin_input = vin.clone();
+                        self.decode_input();
+                    }
+                });
+            }
+            Err(errors) => {
+                for error in errors {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+            }
+        }
+    }
+
+    /// Search every field's option labels; clicking a hit selects it in the builder
+    fn render_option_search(&mut self, ui: &mut egui::Ui) {
+        let model = self.model_override.unwrap_or_else(|| {
+            let code = self.builder.get("Model").map_or("", String::as_str);
+            model_index_for_code(code).unwrap_or(0)
+        });
+        let structure = VEHICLE_MODELS[model].structure;
+        ui.add(
+            egui::TextEdit::singleline(&mut self.option_search)
+                .hint_text("e.g. Tachometer")
+                .desired_width(200.0),
+        );
+        let hits = search_options(structure, &self.decode_maps[model], &self.option_search);
+        if hits.is_empty() && !self.option_search.trim().is_empty() {
+            ui.weak("No option matches");
+        }
+        for (field, code, label) in hits {
+            let text = format!("{}: {} = {}", field.display, code, label);
+            if ui
+                .add(egui::Button::new(text).frame(false))
+                .on_hover_text("Select this option in \"Build a VIN\"")
+                .clicked()
+            {
+                self.builder.insert(field.key, code.to_string());
+            }
+        }
+    }
+
+    /// Decode typed VIN `input` like the manual input; `Ok(None)` when it is empty
+    fn decode_typed(&self, input: &str) -> Result<Option<DecodedVin<'static>>, String> {
+        let parsed = parse_vin_input(input, self.case_sensitive_codes, |code| {
+            self.resolve_model(code).0
+        })?;
+        Ok(parsed.map(|parsed| {
+            DecodedVin::new(parsed.structure, &self.decode_maps[parsed.model], |key| {
+                parsed.entries.get(key).map_or(EMPTY, String::as_str)
+            })
+        }))
+    }
+
+    /// Two VIN inputs decoded into one table, differing fields highlighted
+    fn render_compare(&mut self, ui: &mut egui::Ui) {
+        for (input, hint) in self
+            .compare_inputs
+            .iter_mut()
+            .zip(["First VIN", "Second VIN"])
+        {
+            ui.add(
+                egui::TextEdit::singleline(input)
+                    .desired_width(f32::INFINITY)
+                    .hint_text(hint),
+            );
+        }
+        let decoded: Vec<_> = self
+            .compare_inputs
+            .iter()
+            .map(|input| self.decode_typed(input))
+            .collect();
+        match (&decoded[0], &decoded[1]) {
+            (Ok(Some(a)), Ok(Some(b))) => {
+                ui.add_space(4.0);
+                render_vin_compare(ui, a, b);
+            }
+            (Err(e), _) | (_, Err(e)) => {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+            }
+            _ => {
+                ui.weak("Enter both VINs to compare them field by field");
+            }
+        }
+    }
+
+    /// Step-by-step entry: prompt for each field with its known codes, decode at the end
+    fn render_wizard(&mut self, ui: &mut egui::Ui) {
+        let model = self.model_override.unwrap_or_else(|| {
+            let code = model_code_in_vin(&self.wizard.codes.concat()).to_string();
+            model_index_for_code(&code).unwrap_or(0)
+        });
+        let structure = VEHICLE_MODELS[model].structure;
+        let wizard = &mut self.wizard;
+        let step = wizard.codes.len().min(structure.len() - 1);
+        let field = &structure[step];
+        let options = all_field_options(&structure[step..=step], &self.decode_maps[model]);
+        let codes = options
+            .first()
+            .map_or(&[][..], |(_, codes)| codes.as_slice());
+
+        ui.label(format!(
+            "Step {} of {}: enter {} ({} char)",
+            step + 1,
+            structure.len(),
+            field.display,
+            field.len
+        ));
+        if !codes.is_empty() {
+            let hint: Vec<String> = codes
+                .iter()
+                .map(|(code, label)| format!("{}={}", code, label))
+                .collect();
+            ui.add(egui::Label::new(egui::RichText::new(hint.join(", ")).weak()).wrap());
+        }
+        let mut next = false;
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut wizard.input)
+                    .char_limit(field.len)
+                    .desired_width(60.0)
+                    .font(egui::TextStyle::Monospace),
+            );
+            next = response.lost_focus()
+                && ui.input(|i| i.key_pressed(SHORTCUT_DECODE.keys.logical_key));
+            if ui.button("Next").clicked() {
+                next = true;
+            }
+            if ui
+                .add_enabled(!wizard.codes.is_empty(), egui::Button::new("Back"))
+                .clicked()
+            {
+                wizard.input = wizard.codes.pop().unwrap_or_default();
+                wizard.error = None;
+            }
+            if ui.button("Start over").clicked() {
+                *wizard = VinWizard::default();
+            }
+            if next {
+                response.request_focus();
+            }
+        });
+        if !wizard.codes.is_empty() {
+            ui.monospace(format!("VIN so far: {}", wizard.codes.concat()));
+        }
+        if let Some(ref error) = wizard.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        if next {
+            let code = normalize_code(wizard.input.trim(), self.case_sensitive_codes).into_owned();
+            if code.chars().count() != field.len {
+                wizard.error = Some(format!(
+                    "{} needs exactly {} character(s)",
+                    field.display, field.len
+                ));
+                return;
+            }
+            wizard.error = None;
+            wizard.input.clear();
+            wizard.codes.push(code);
+            if wizard.codes.len() == structure.len() {
+                self.vin_input = std::mem::take(&mut self.wizard).codes.concat();
+                self.decode_input();
+            }
+        }
+    }
+
+    /// Load and decode the VIN section of the file at `file_path`
+    ///
+    /// Reads and parses on a background thread; the result is picked up by
+    /// `poll_load`. Ignored while a load is already running.
+    fn load_file(&mut self, ctx: &egui::Context) {
+        if self.loading() {
+            return;
+        }
+        if self.file_path.as_os_str().is_empty() {
+            self.file_error = Some(NO_PATH_HINT.to_string());
+            return;
+        }
+        let path = expand_path(&self.file_path);
+        let (tx, rx) = std::sync::mpsc::channel();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let result = read_carparts(&path);
+            let _ = tx.send((path, result));
+            ctx.request_repaint();
+        });
+        self.load_rx = Some(rx);
+    }
+
+    /// Take over the theme and the last loaded file of the previous run; a file
+    /// that has since gone keeps the default path
+    fn restore_settings(&mut self, settings: &settings::Settings) {
+        self.theme = settings.theme;
+        // Kept even when missing now, so a session without a load doesn't forget it
+        self.loaded_path = settings.last_path.clone();
+        if let Some(path) = settings.existing_path() {
+            self.set_file_path(path.clone());
+        }
+    }
+
+    /// Remember the theme, the last loaded file and the window geometry
+    fn save_settings(&self, ctx: &egui::Context) {
+        let (size, pos) = ctx.input(|i| {
+            let viewport = i.viewport();
+            (
+                viewport.inner_rect.map(|r| [r.width(), r.height()]),
+                viewport.outer_rect.map(|r| [r.min.x, r.min.y]),
+            )
+        });
+        let saved = settings::save(&settings::Settings {
+            last_path: self.loaded_path.clone(),
+            theme: self.theme,
+            window_size: size,
+            window_pos: pos,
+        });
+        if let Err(e) = saved {
+            eprintln!("Failed to save settings: {}", e);
+        }
+    }
+
+    /// Load the remembered or default carparts.txt at startup when it exists
+    fn start_auto_load(&mut self, ctx: &egui::Context) {
+        if expand_path(&self.file_path).is_file() {
+            self.load_file(ctx);
+            self.auto_loading = self.loading();
+        }
+    }
+
+    /// Start or stop watching the file at `file_path`
+    fn set_watching(&mut self, on: bool, now: f64) {
+        if !on {
+            self.watch = None;
+            return;
+        }
+        if self.file_path.as_os_str().is_empty() {
+            self.file_error = Some(NO_PATH_HINT.to_string());
+            return;
+        }
+        let path = expand_path(&self.file_path);
+        match std::fs::metadata(&path).and_then(|m| m.modified()) {
+            Ok(modified) => {
+                self.watch = Some(FileWatch {
+                    path,
+                    modified: Some(modified),
+                    last_check: now,
+                });
+            }
+            Err(e) => self.file_error = Some(format!("Can't watch {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Reload when the watched file changed; stop watching when it's gone
+    fn poll_watch(&mut self, ctx: &egui::Context, now: f64) {
+        let busy = self.loading();
+        let Some(ref mut watch) = self.watch else {
+            return;
+        };
+        ctx.request_repaint_after(std::time::Duration::from_millis(250));
+        if now - watch.last_check < WATCH_INTERVAL {
+            return;
+        }
+        watch.last_check = now;
+        match std::fs::metadata(&watch.path).and_then(|m| m.modified()) {
+            // Picked up again at the next check when a load is still running
+            Ok(modified) if Some(modified) != watch.modified && !busy => {
+                watch.modified = Some(modified);
+                self.load_file(ctx);
+            }
+            Ok(_) => {}
+            Err(e) => {
+                let error = format!("Stopped watching {}: {}", watch.path.display(), e);
+                self.watch = None;
+                self.last_error = Some(error.clone());
+                self.file_error = Some(error);
+            }
+        }
+    }
+
+    /// Whether a background load is in flight
+    fn loading(&self) -> bool {
+        self.load_rx.is_some()
+    }
+
+    /// Load a dropped file: from its path when it has one, otherwise from
+    /// its bytes (e.g. dropped from a browser)
+    fn load_dropped(&mut self, ctx: &egui::Context, df: egui::DroppedFile) {
+        if let Some(path) = df.path {
+            self.set_file_path(path);
+            self.load_file(ctx);
+        } else if let Some(bytes) = df.bytes {
+            let name = if df.name.is_empty() {
+                "the dropped file"
+            } else {
+                &df.name
+            };
+            let (result, diagnostics) = parse_carparts(bytes.to_vec(), name);
+            self.apply_load_result(result, diagnostics);
+        }
+    }
+
+    /// Apply the result of a finished background load
+    fn poll_load(&mut self) {
+        let Some(ref rx) = self.load_rx else {
+            return;
+        };
+        match rx.try_recv() {
+            Ok((path, (result, diagnostics))) => {
+                self.load_rx = None;
+                if result.is_ok() {
+                    self.loaded_path = Some(path);
+                }
+                self.apply_load_result(result, diagnostics);
+            }
+            Err(std::sync::mpsc::TryRecvError::Empty) => {}
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                self.load_rx = None;
+                self.apply_load_result(
+                    Err("Loading stopped unexpectedly".to_string()),
+                    ParseDiagnostics::default(),
+                );
+            }
+        }
+    }
+
+    /// Show loaded entries or the load error
+    fn apply_load_result(
+        &mut self,
+        result: Result<Vehicles, String>,
+        diagnostics: ParseDiagnostics,
+    ) {
+        self.diagnostics = Some(diagnostics);
+        self.inspected_section = None;
+        self.undo = None;
+        let auto_loaded = std::mem::take(&mut self.auto_loading);
+        match result {
+            Ok(vehicles) => {
+                self.auto_loaded = auto_loaded;
+                // Stay on the same vehicle when a reload still has it
+                if self.vehicle_index >= vehicles.len() {
+                    self.vehicle_index = 0;
+                }
+                let entries = vehicles[self.vehicle_index].clone();
+                if self.watch.is_some() {
+                    self.log_changes(&entries);
+                }
+                self.vingen4_entries = Some(entries);
+                self.vehicles = vehicles;
+                self.file_error = None;
+                self.last_source = LastSource::File;
+            }
+            Err(e) => {
+                self.auto_loaded = false;
+                self.last_error = Some(e.clone());
+                self.file_error = Some(e);
+                self.vingen4_entries = None;
+                self.vehicles.clear();
+                self.vehicle_index = 0;
+            }
+        }
+    }
+
+    /// Decode the pasted hex dump as if it had been loaded from a file
+    fn load_hex(&mut self) {
+        let mut diagnostics = ParseDiagnostics::default();
+        let result = guard_parse(|| parse_vingen4_hex(&self.hex_input, &mut diagnostics));
+        self.apply_load_result(result.map(|entries| vec![entries]), diagnostics);
+    }
+
+    /// Error report for support requests, without the full save path
+    fn error_report(&self) -> String {
+        let path = expand_path(&self.file_path);
+        let basename = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let error = self
+            .last_error
+            .as_deref()
+            .unwrap_or("(none)")
+            .replace(&path.display().to_string(), &basename);
+        let mut report = format!(
+            "MWC VIN Decoder v{}\nError: {}\nFile: {}\n",
+            env!("CARGO_PKG_VERSION"),
+            error,
+            basename
+        );
+        if let Some(ref diag) = self.diagnostics {
+            report.push_str(&format!(
+                "Format: {}\n",
+                diag.vin_tag.as_deref().unwrap_or("no VIN section")
+            ));
+            for line in diag.detail_lines() {
+                report.push_str(&line);
+                report.push('\n');
+            }
+            let mut unique: Vec<&str> = Vec::new();
+            for (tag, _) in &diag.sections {
+                if !unique.contains(&tag.as_str()) {
+                    unique.push(tag);
+                }
+            }
+            report.push_str(&format!(
+                "Tags found: {} entries, {} distinct\n",
+                diag.sections.len(),
+                unique.len()
+            ));
+            const MAX_TAGS: usize = 40;
+            report.push_str(&unique[..unique.len().min(MAX_TAGS)].join(", "));
+            if unique.len() > MAX_TAGS {
+                report.push_str(", ...");
+            }
+            report.push('\n');
+        }
+        report
+    }
+
+    /// Picker over every entry of the loaded file, showing the chosen one's
+    /// key/value pairs
+    fn render_section_inspector(&mut self, ui: &mut egui::Ui) {
+        let Some(ref diag) = self.diagnostics else {
+            return;
+        };
+        let section_name = |i: usize| {
+            let (tag, range) = &diag.sections[i];
+            format!("#{} {} ({} bytes)", i + 1, tag, range.len())
+        };
+        let mut selected = self.inspected_section.as_ref().map(|(i, _)| *i);
+        egui::ComboBox::from_id_salt("section_picker")
+            .selected_text(selected.map_or("Pick a section".to_string(), section_name))
+            .width(ui.available_width().min(300.0))
+            .show_ui(ui, |ui| {
+                for i in 0..diag.sections.len() {
+                    ui.selectable_value(&mut selected, Some(i), section_name(i));
+                }
+            });
+        if let Some(i) =
+            selected.filter(|&i| self.inspected_section.as_ref().map(|(s, _)| *s) != Some(i))
+        {
+            let entries = diag.section_body(i).map_or_else(
+                || Err("No data for this section".to_string()),
+                parse_section,
+            );
+            self.inspected_section = Some((i, entries));
+        }
+        match self.inspected_section {
+            Some((_, Ok(ref entries))) if entries.is_empty() => {
+                ui.weak("No entries");
+            }
+            Some((_, Ok(ref entries))) => {
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.sort_section_by_key, "Sort by key")
+                        .on_hover_text("List entries alphabetically instead of in file order");
+                    ui.label("Float decimals:");
+                    ui.add(egui::DragValue::new(&mut self.float_decimals).range(0..=9))
+                        .on_hover_text(
+                            "Rounding of float values here and in the results; \
+                             hover a value for its full precision",
+                        );
+                });
+                egui::ScrollArea::vertical()
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("section_entries")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for (key, value) in section_order(entries, self.sort_section_by_key)
+                                {
+                                    ui.monospace(key);
+                                    let shown = round_typed_float(value, self.float_decimals);
+                                    if shown != value.as_str() {
+                                        ui.monospace(shown.as_ref())
+                                            .on_hover_text(format!("Raw: {}", value));
+                                    } else {
+                                        ui.monospace(value);
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            }
+            Some((_, Err(ref e))) => {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+            }
+            None => {}
+        }
+    }
+
+    /// Whether `file_path` points to an existing file, re-checked only when the path changed
+    fn file_path_exists(&mut self) -> bool {
+        if self.path_check.0 != self.file_path {
+            self.expanded_path = expand_path(&self.file_path);
+            let exists = self.expanded_path.is_file();
+            self.path_check = (self.file_path.clone(), exists);
+        }
+        self.path_check.1
+    }
+
+    /// Model to decode with for a Model field code, plus a note when falling back
+    fn resolve_model(&self, model_code: &str) -> (usize, Option<String>) {
+        if let Some(index) = self.model_override {
+            return (index, None);
+        }
+        match model_index_for_code(model_code) {
+            Some(index) => (index, None),
+            None if model_code.is_empty() => (0, None),
+            None => (
+                0,
+                Some(format!(
+                    "Unrecognized model code \"{}\", decoded as {}",
+                    model_code, VEHICLE_MODELS[0].name
+                )),
+            ),
+        }
+    }
+
+    /// Render the decode table with the tables of the resolved model
+    fn render_results(
+        &self,
+        ui: &mut egui::Ui,
+        sort: &mut TableSort,
+        decoded: &DecodedVin,
+    ) -> egui::Rect {
+        if let (_, Some(note)) = self.resolve_model(decoded.value("Model")) {
+            ui.vertical_centered(|ui| {
+                ui.colored_label(ui.visuals().warn_fg_color, note);
+            });
+            ui.add_space(4.0);
+        }
+        render_vin_table(
+            ui,
+            decoded,
+            sort,
+            TableView {
+                decoded_only: self.decoded_only,
+                serial_display: self.serial_display,
+                plant_locations: self.plant_locations,
+                positions: self.show_positions,
+                pinned: self.pinned.as_ref(),
+            },
+        )
+    }
+
+    /// The shown decode as exports see it (serial redacted when asked)
+    fn decoded_for_export(&self) -> Option<DecodedVin<'static>> {
+        let decoded = self
+            .current_decoded(self.redact_serial)?
+            .with_notes(&self.notes);
+        Some(match self.last_source {
+            LastSource::File => decoded.with_source(DecodeSource::File),
+            LastSource::Vin => decoded.with_source(DecodeSource::Manual),
+            LastSource::None => decoded,
+        })
+    }
+
+    /// The shown decode with the tables of its model
+    fn current_decoded(&self, redact: bool) -> Option<DecodedVin<'static>> {
+        Some(self.decode_values(&self.current_values()?, redact))
+    }
+
+    /// File and typed decodes for the side-by-side view, `None` unless it's
+    /// on and both are present
+    fn split_decoded(&self, redact: bool) -> Option<(DecodedVin<'static>, DecodedVin<'static>)> {
+        if !self.split_view {
+            return None;
+        }
+        let file = self.decode_values(&self.file_values()?, redact);
+        let typed = self.decode_values(&self.typed_values()?, redact);
+        Some((file, typed))
+    }
+
+    /// Decode field values with the tables of their model
+    fn decode_values(
+        &self,
+        values: &HashMap<&str, Cow<'_, str>>,
+        redact: bool,
+    ) -> DecodedVin<'static> {
+        let get_value = |key: &str| values.get(key).map_or(EMPTY, |v| v.as_ref());
+        let (model, _) = self.resolve_model(get_value("Model"));
+        DecodedVin::new(
+            VEHICLE_MODELS[model].structure,
+            &self.decode_maps[model],
+            export::redact_serial(get_value, redact),
+        )
+    }
+
+    /// Side column with the pinned decode, fields that differ from the
+    /// current one highlighted
+    fn render_pinned(&mut self, ui: &mut egui::Ui) {
+        let Some(ref pinned) = self.pinned else {
+            return;
+        };
+        let current = self.current_decoded(false);
+        let mut unpin = false;
+        ui.horizontal(|ui| {
+            ui.strong("📌 Pinned");
+            unpin = ui
+                .button("Unpin")
+                .on_hover_text("Stop comparing against this VIN")
+                .clicked();
+        });
+        ui.monospace(pinned.complete_vin());
+        ui.separator();
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("pinned_grid").striped(true).show(ui, |ui| {
+                for (key, display, code, status) in pinned.iter() {
+                    let differs = current.as_ref().is_some_and(|c| c.value(key) != code);
+                    let name = egui::RichText::new(display);
+                    if differs {
+                        ui.label(name.color(PINNED_DIFF_COLOR))
+                            .on_hover_text("Differs from the current decode");
+                    } else {
+                        ui.label(name);
+                    }
+                    ui.monospace(code);
+                    ui.label(status.label());
+                    ui.end_row();
+                }
+            });
+        });
+        if unpin {
+            self.pinned = None;
+        }
+    }
+
+    /// Write every export format into one folder, then capture the PNG;
+    /// the status line lists the written files once the image is saved
+    fn export_bundle(&mut self) -> Option<ScreenshotTarget> {
+        let decoded = self.decoded_for_export()?;
+        let base = save::pick_bundle_base()?;
+        match export::write_bundle(&base, &decoded) {
+            Ok(written) => Some(ScreenshotTarget::Bundle {
+                png: base.with_extension("png"),
+                written,
+            }),
+            Err(e) => {
+                self.export_status = Some(e);
+                None
+            }
+        }
+    }
+
+    /// Result of "Validate against loaded file" while the input is unchanged
+    fn render_validation(&self, ui: &mut egui::Ui) {
+        let Some((input, result)) = &self.validation else {
+            return;
+        };
+        if *input != self.vin_input {
+            return;
+        }
+        ui.add_space(4.0);
+        match result {
+            Ok(mismatches) if mismatches.is_empty() => {
+                ui.colored_label(ok_color(ui.visuals()), "✔ Matches loaded car");
+            }
+            Ok(mismatches) => {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    format!("✘ {} field(s) differ from the loaded car", mismatches.len()),
+                );
+                egui::Grid::new("validation_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("Field");
+                        ui.strong("File");
+                        ui.strong("Typed");
+                        ui.end_row();
+                        for m in mismatches {
+                            ui.label(m.field);
+                            ui.monospace(&m.file);
+                            ui.monospace(&m.typed);
+                            ui.end_row();
+                        }
+                    });
+            }
+            Err(e) => {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+            }
+        }
+    }
+
+    /// Sidebar listing the fields changed by watched reloads, newest first
+    fn render_change_log(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.strong("Recent changes");
+            if ui
+                .add_enabled(!self.change_log.is_empty(), egui::Button::new("Clear"))
+                .clicked()
+            {
+                self.change_log.clear();
+            }
+        });
+        ui.separator();
+        if self.change_log.is_empty() {
+            ui.weak("Changed fields appear here when the watched file is saved");
+            return;
+        }
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for change in &self.change_log {
+                ui.horizontal(|ui| {
+                    ui.small(clock_time(change.at)).on_hover_text("UTC");
+                    ui.strong(change.field);
+                });
+                ui.monospace(format!("{} → {}", change.old, change.new));
+                ui.add_space(4.0);
+            }
+        });
+    }
+
+    /// Bottom strip: source, file name, decode coverage and the live-watch state
+    fn render_status_bar(&self, ui: &mut egui::Ui, now: f64) {
+        ui.horizontal(|ui| {
+            let source = match self.last_source {
+                LastSource::File => "File",
+                LastSource::Vin => "Manual",
+                LastSource::None => "None",
+            };
+            ui.small(format!("Source: {}", source));
+            if matches!(self.last_source, LastSource::File) {
+                if let Some(name) = expand_path(&self.file_path).file_name() {
+                    ui.separator();
+                    ui.small(name.to_string_lossy());
+                }
+            }
+            if let Some(decoded) = self.decoded_for_export() {
+                // Fields with a decode table that resolved to a label
+                let (known, total) = decoded
+                    .iter()
+                    .filter(|(.., status)| *status != FieldStatus::Raw)
+                    .fold((0, 0), |(known, total), (.., status)| {
+                        let ok = matches!(status, FieldStatus::Decoded(_) | FieldStatus::Standard);
+                        (known + usize::from(ok), total + 1)
+                    });
+                ui.separator();
+                ui.small(format!("{}/{} fields decoded", known, total));
+            }
+            if self.watch.is_some() {
+                ui.separator();
+                // Pulse once per second
+                let pulse = (now * std::f64::consts::TAU).sin() * 0.5 + 0.5;
+                let alpha = (120.0 + 135.0 * pulse) / 255.0;
+                ui.colored_label(
+                    ok_color(ui.visuals()).gamma_multiply(alpha as f32),
+                    egui::RichText::new("● live").small(),
+                )
+                .on_hover_text("Watching the file for changes");
+            }
+        });
+    }
+
+    /// Crop a delivered viewport screenshot to the results and copy or save it
+    fn handle_screenshot(&mut self, ctx: &egui::Context) {
+        if self.pending_screenshot.is_none() {
+            return;
+        }
+        let image = ctx.input(|i| {
+            i.raw.events.iter().find_map(|e| match e {
+                egui::Event::Screenshot { image, .. } => Some(image.clone()),
+                _ => None,
+            })
+        });
+        let (Some(image), Some(rect)) = (image, self.results_rect) else {
+            return;
+        };
+        let Some(target) = self.pending_screenshot.take() else {
+            return;
+        };
+        let cropped = image.region(&rect, Some(ctx.pixels_per_point()));
+        match target {
+            ScreenshotTarget::Clipboard => {
+                ctx.copy_image(cropped);
+                self.export_status = Some("Image copied to clipboard".to_string());
+            }
+            ScreenshotTarget::File => match save::save_png_with_dialog(&cropped) {
+                Ok(Some(path)) => self.export_status = Some(format!("Saved {}", path.display())),
+                Ok(None) => {}
+                Err(e) => self.export_status = Some(e),
+            },
+            ScreenshotTarget::Bundle { png, mut written } => {
+                let result = save::save_png(&cropped, &png);
+                if result.is_ok() {
+                    written.push(png);
+                }
+                let names: Vec<String> = written
+                    .iter()
+                    .filter_map(|p| p.file_name())
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .collect();
+                self.export_status = Some(match result {
+                    Ok(()) => format!("Saved {}", names.join(", ")),
+                    Err(e) => format!("Saved {}; {}", names.join(", "), e),
+                });
+            }
+        }
+    }
+
+    /// Export the current decode via a save dialog and remember the format
+    fn export(&mut self, format: ExportFormat) {
+        let Some(decoded) = self.decoded_for_export() else {
+            return;
+        };
+        let contents = format.render(&decoded);
+        self.last_export = format;
+        match save::save_with_dialog(format, &contents) {
+            Ok(Some(path)) => self.export_status = Some(format!("Saved {}", path.display())),
+            Ok(None) => {}
+            Err(e) => self.export_status = Some(e),
+        }
+    }
+}
+
+/// Default values (including carparts.txt path)
+impl Default for VinApp {
+    fn default() -> Self {
+        Self {
+            vin_input: String::new(),
+            entries: None,
+            vin_error: None,
+            alignments: None,
+            file_path: VinApp::default_file_path(),
+            file_path_text: VinApp::default_file_path().to_string_lossy().into_owned(),
+            vingen4_entries: None,
+            vehicles: Vec::new(),
+            vehicle_index: 0,
+            last_source: LastSource::None,
+            decode_maps: VEHICLE_MODELS.iter().map(VehicleModel::tables).collect(),
+            model_override: None,
+            file_error: None,
+            last_export: ExportFormat::Json,
+            export_status: None,
+            template_status: None,
+            results_rect: None,
+            pending_screenshot: None,
+            redact_serial: false,
+            vin_hint: None,
+            path_check: (PathBuf::new(), false),
+            expanded_path: PathBuf::new(),
+            show_about: false,
+            show_shortcuts: false,
+            last_error: None,
+            diagnostics: None,
+            hex_input: String::new(),
+            table_sort: TableSort::default(),
+            undo: None,
+            builder: HashMap::new(),
+            option_search: String::new(),
+            decoded_only: false,
+            serial_display: SerialDisplay::default(),
+            plant_locations: false,
+            show_positions: false,
+            split_view: false,
+            auto_loading: false,
+            auto_loaded: false,
+            load_rx: None,
+            queued_drop: None,
+            wizard: VinWizard::default(),
+            compare_inputs: Default::default(),
+            case_sensitive_codes: false,
+            watch: None,
+            change_log: Vec::new(),
+            notes: String::new(),
+            pinned: None,
+            history: Vec::new(),
+            history_pos: None,
+            pixels_per_point: None,
+            theme: Theme::default(),
+            loaded_path: None,
+            validation: None,
+            show_number_readings: false,
+            inspected_section: None,
+            sort_section_by_key: false,
+            float_decimals: DEFAULT_FLOAT_DECIMALS,
+        }
+    }
+}
+
+/// Colour scheme of the window
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, serde::Serialize, serde::Deserialize)]
+enum Theme {
+    /// The dark 1999 workshop look
+    #[default]
+    Workshop,
+    /// Bright variant for well-lit rooms
+    Light,
+}
+
+impl Theme {
+    /// The other theme, for the toggle button
+    fn toggled(self) -> Self {
+        match self {
+            Theme::Workshop => Theme::Light,
+            Theme::Light => Theme::Workshop,
+        }
+    }
+
+    /// Toggle button text, naming the theme it switches to
+    fn toggle_label(self) -> &'static str {
+        match self {
+            Theme::Workshop => "☀ Light",
+            Theme::Light => "🌙 Workshop",
+        }
+    }
+
+    /// Window colours of the theme
+    fn visuals(self) -> egui::Visuals {
+        match self {
+            Theme::Workshop => {
+                // Dunkle 90er Werkstatt-Farben
+                let bg_color = egui::Color32::from_rgb(30, 30, 32); // Dunkler Hintergrund
+                let panel_color = egui::Color32::from_rgb(40, 40, 42); // Panel Hintergrund
+                let border_color = egui::Color32::from_rgb(100, 100, 105); // Grauer Rahmen
+                let werkstatt_orange = egui::Color32::from_rgb(200, 120, 40); // Werkstatt-Orange
+                let metal_dark = egui::Color32::from_rgb(60, 60, 65); // Dunkles Metall
+
+                let mut visuals = egui::Visuals::dark();
+                visuals.panel_fill = bg_color;
+                visuals.window_fill = bg_color;
+                visuals.faint_bg_color = panel_color;
+                visuals.extreme_bg_color = egui::Color32::from_rgb(50, 50, 52);
+                visuals.widgets.noninteractive.bg_fill = egui::Color32::from_rgb(45, 45, 47);
+                visuals.widgets.inactive.bg_fill = metal_dark;
+                visuals.widgets.hovered.bg_fill = egui::Color32::from_rgb(70, 70, 75);
+                visuals.widgets.active.bg_fill = werkstatt_orange;
+                visuals.selection.bg_fill = werkstatt_orange;
+                visuals.window_stroke = egui::Stroke::new(2.0, border_color);
+                visuals.widgets.noninteractive.bg_stroke = egui::Stroke::new(2.0, border_color);
+                visuals.warn_fg_color = werkstatt_orange;
+                visuals.error_fg_color = egui::Color32::from_rgb(255, 100, 100);
+                visuals
+            }
+            Theme::Light => {
+                let bg_color = egui::Color32::from_rgb(236, 234, 228);
+                let border_color = egui::Color32::from_rgb(140, 140, 145);
+                // Lighter fill so dark button text stays readable, darker for text
+                let orange_fill = egui::Color32::from_rgb(240, 160, 80);
+                let orange_text = egui::Color32::from_rgb(170, 85, 0);
+
+                let mut visuals = egui::Visuals::light();
+                visuals.panel_fill = bg_color;
+                visuals.window_fill = bg_color;
+                visuals.faint_bg_color = egui::Color32::from_rgb(226, 224, 218);
+                visuals.extreme_bg_color = egui::Color32::from_rgb(252, 252, 250);
+                visuals.widgets.noninteractive.bg_fill = egui::Color32::from_rgb(246, 245, 241);
+                visuals.widgets.noninteractive.fg_stroke.color =
+                    egui::Color32::from_rgb(30, 30, 32);
+                visuals.widgets.inactive.bg_fill = egui::Color32::from_rgb(214, 212, 206);
+                visuals.widgets.hovered.bg_fill = egui::Color32::from_rgb(200, 198, 192);
+                visuals.widgets.active.bg_fill = orange_fill;
+                visuals.selection.bg_fill = orange_fill;
+                visuals.selection.stroke.color = egui::Color32::from_rgb(30, 30, 32);
+                visuals.window_stroke = egui::Stroke::new(2.0, border_color);
+                visuals.widgets.noninteractive.bg_stroke = egui::Stroke::new(2.0, border_color);
+                visuals.warn_fg_color = orange_text;
+                visuals.error_fg_color = egui::Color32::from_rgb(180, 30, 30);
+                visuals
+            }
         }
     }
 }
 
-/// Default values (including carparts.txt path)
-impl Default for VinApp {
-    fn default() -> Self {
-        Self {
-            vin_input: String::new(),
-            entries: None,
-            vin_error: None,
-            file_path: VinApp::default_file_path(),
-            vingen4_entries: None,
-            last_source: LastSource::None,
-            decode_map: decode_map(),
-            file_error: None,
-        }
+/// Colour for "matches" and other good news, readable on the theme's background
+fn ok_color(visuals: &egui::Visuals) -> egui::Color32 {
+    if visuals.dark_mode {
+        egui::Color32::from_rgb(80, 200, 80)
+    } else {
+        egui::Color32::from_rgb(30, 130, 30)
+    }
+}
+
+/// Bordered frame of the results and the input sections
+fn panel_frame(ui: &egui::Ui) -> egui::Frame {
+    let visuals = &ui.visuals().widgets.noninteractive;
+    egui::Frame::new()
+        .corner_radius(2.0)
+        .fill(visuals.bg_fill)
+        .stroke(egui::Stroke::new(3.0, visuals.bg_stroke.color))
+}
+
+/// Background of the error boxes
+fn error_fill(visuals: &egui::Visuals) -> egui::Color32 {
+    if visuals.dark_mode {
+        egui::Color32::from_rgb(80, 20, 20)
+    } else {
+        egui::Color32::from_rgb(250, 222, 222)
     }
 }
 
+/// `theme` colours with the fixed font sizes
+fn apply_style(ctx: &egui::Context, theme: Theme) {
+    let mut style = (*ctx.style()).clone();
+    style.visuals = theme.visuals();
+
+    style.text_styles = [
+        (egui::TextStyle::Heading, egui::FontId::proportional(20.0)),
+        (egui::TextStyle::Body, egui::FontId::proportional(14.0)),
+        (egui::TextStyle::Monospace, egui::FontId::monospace(14.0)),
+        (egui::TextStyle::Button, egui::FontId::proportional(14.0)),
+        (egui::TextStyle::Small, egui::FontId::proportional(12.0)),
+    ]
+    .into();
+    ctx.set_style(style);
+}
+
 /// GUI update loop
 impl eframe::App for VinApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // 1999 Werkstatt-Style in Dark Mode
-        let mut style = (*ctx.style()).clone();
-
-        // Dunkle 90er Werkstatt-Farben
-        let bg_color = egui::Color32::from_rgb(30, 30, 32); // Dunkler Hintergrund
-        let panel_color = egui::Color32::from_rgb(40, 40, 42); // Panel Hintergrund
-        let border_color = egui::Color32::from_rgb(100, 100, 105); // Grauer Rahmen
-        let werkstatt_orange = egui::Color32::from_rgb(200, 120, 40); // Werkstatt-Orange
-        let metal_dark = egui::Color32::from_rgb(60, 60, 65); // Dunkles Metall
-
-        style.visuals.panel_fill = bg_color;
-        style.visuals.window_fill = bg_color;
-        style.visuals.faint_bg_color = panel_color;
-        style.visuals.extreme_bg_color = egui::Color32::from_rgb(50, 50, 52);
-        style.visuals.widgets.noninteractive.bg_fill = egui::Color32::from_rgb(45, 45, 47);
-        style.visuals.widgets.inactive.bg_fill = metal_dark;
-        style.visuals.widgets.hovered.bg_fill = egui::Color32::from_rgb(70, 70, 75);
-        style.visuals.widgets.active.bg_fill = werkstatt_orange;
-        style.visuals.selection.bg_fill = werkstatt_orange;
-        style.visuals.window_stroke = egui::Stroke::new(2.0, border_color);
-        style.visuals.widgets.noninteractive.bg_stroke = egui::Stroke::new(2.0, border_color);
-
-        style.text_styles = [
-            (egui::TextStyle::Heading, egui::FontId::proportional(20.0)),
-            (egui::TextStyle::Body, egui::FontId::proportional(14.0)),
-            (egui::TextStyle::Monospace, egui::FontId::monospace(14.0)),
-            (egui::TextStyle::Button, egui::FontId::proportional(14.0)),
-            (egui::TextStyle::Small, egui::FontId::proportional(12.0)),
-        ]
-        .into();
-        ctx.set_style(style);
+        // Re-apply the style whenever the scale factor changes, e.g. when the
+        // window is dragged to a monitor with a different DPI
+        let pixels_per_point = ctx.pixels_per_point();
+        if self.pixels_per_point != Some(pixels_per_point) {
+            apply_style(ctx, self.theme);
+            self.pixels_per_point = Some(pixels_per_point);
+            ctx.request_repaint();
+        }
 
         // Handle file drag-and-drop: accept a dropped `carparts.txt`file
         // and attempt to parse it as a VINGen4 file. We prefer the first dropped
         // file with a native path, otherwise fall back to the first bytes payload.
+        // A drop during a load waits for it to finish.
         let dropped = ctx.input(|i| i.raw.dropped_files.clone());
-        if !dropped.is_empty() {
-            for df in dropped.into_iter() {
-                if let Some(path) = df.path {
-                    let path_str = path.display().to_string();
-                    self.file_path = path_str.clone();
-                    if !std::path::Path::new(&self.file_path).exists() {
-                        self.file_error = Some(format!("File not found: {}", self.file_path));
-                        self.vingen4_entries = None;
-                    } else {
-                        match parse_vingen4_file(&self.file_path) {
-                            Some(entries) => {
-                                self.vingen4_entries = Some(entries);
-                                self.file_error = None;
-                                self.last_source = LastSource::File;
-                            }
-                            None => {
-                                self.file_error = Some("No VIN data found in file".to_string());
-                                self.vingen4_entries = None;
-                            }
-                        }
-                    }
-                    break;
-                }
+        if let Some(df) = dropped
+            .into_iter()
+            .find(|df| df.path.is_some() || df.bytes.is_some())
+        {
+            self.queued_drop = Some(df);
+        }
 
-                // If there's no native path but bytes were dropped (e.g., from the web),
-                // write them to a temp file and attempt to parse that.
-                if let Some(bytes) = df.bytes.clone() {
-                    use std::io::Write;
-                    let tmp = std::env::temp_dir().join("dropped_carparts.txt");
-                    if let Ok(mut f) = std::fs::File::create(&tmp) {
-                        let _ = f.write_all(&bytes);
-                        self.file_path = tmp.display().to_string();
-                        match parse_vingen4_file(&self.file_path) {
-                            Some(entries) => {
-                                self.vingen4_entries = Some(entries);
-                                self.file_error = None;
-                                self.last_source = LastSource::File;
-                            }
-                            None => {
-                                self.file_error = Some("No VIN data found in file".to_string());
-                                self.vingen4_entries = None;
-                            }
+        if ctx.input(|i| i.viewport().close_requested()) {
+            self.save_settings(ctx);
+        }
+
+        self.poll_load();
+        if !self.loading() {
+            if let Some(df) = self.queued_drop.take() {
+                self.load_dropped(ctx, df);
+            }
+        }
+        let now = ctx.input(|i| i.time);
+        self.poll_watch(ctx, now);
+        let busy = self.loading();
+        self.handle_screenshot(ctx);
+
+        // Ctrl+S repeats the last export, unless a text field wants the keys
+        let mut export_request = None;
+        let mut screenshot_request = None;
+        let mut bundle_request = false;
+        let mut results_rect = None;
+        let mut table_sort = self.table_sort;
+        if !ctx.wants_keyboard_input() && SHORTCUT_REPEAT_EXPORT.consume(ctx) {
+            export_request = Some(self.last_export);
+        }
+        if !ctx.wants_keyboard_input() && SHORTCUT_CHEAT_SHEET.consume(ctx) {
+            self.show_shortcuts = !self.show_shortcuts;
+        }
+
+        // One level of undo after Clear/Reset, offered for a few seconds
+        if let Some(ref state) = self.undo {
+            let remaining = state.saved_at + UNDO_SECONDS - now;
+            if remaining <= 0.0 {
+                self.undo = None;
+            } else {
+                ctx.request_repaint_after(std::time::Duration::from_secs_f64(remaining));
+            }
+        }
+        let mut undo_request =
+            self.undo.is_some() && !ctx.wants_keyboard_input() && SHORTCUT_UNDO.consume(ctx);
+
+        egui::Window::new("Keyboard shortcuts")
+            .open(&mut self.show_shortcuts)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("shortcuts_grid")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for shortcut in SHORTCUTS {
+                            ui.monospace(ctx.format_shortcut(&shortcut.keys));
+                            ui.label(shortcut.description);
+                            ui.end_row();
                         }
+                    });
+            });
+
+        let mut copy_report = false;
+        egui::Window::new("About")
+            .open(&mut self.show_about)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                render_about(ui);
+                let warnings = &config::get().warnings;
+                if !warnings.is_empty() {
+                    ui.add_space(6.0);
+                    ui.strong(format!("{} problems", config::CONFIG_FILE));
+                    for warning in warnings {
+                        ui.colored_label(ui.visuals().warn_fg_color, warning);
                     }
-                    break;
                 }
-            }
+                ui.separator();
+                copy_report = ui
+                    .button("Copy last error")
+                    .on_hover_text("Copy an error report (without your full save path) for support")
+                    .clicked();
+            });
+        if copy_report {
+            ctx.copy_text(self.error_report());
+        }
+
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            self.render_status_bar(ui, now);
+        });
+
+        if self.pinned.is_some() {
+            egui::SidePanel::left("pinned")
+                .default_width(240.0)
+                .show(ctx, |ui| self.render_pinned(ui));
+        }
+
+        if self.watch.is_some() || !self.change_log.is_empty() {
+            egui::SidePanel::right("change_log")
+                .default_width(220.0)
+                .show(ctx, |ui| self.render_change_log(ui));
         }
 
         egui::CentralPanel::default().show(ctx, |ui| {
             egui::ScrollArea::vertical()
                 .auto_shrink([false, false])
                 .show(ui, |ui| {
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Min), |ui| {
+                        ui.toggle_value(&mut self.show_about, "ℹ About")
+                            .on_hover_text("Version, VIN layout and where to find carparts.txt");
+                        if ui
+                            .button(self.theme.toggle_label())
+                            .on_hover_text("Switch between the dark workshop and the light theme")
+                            .clicked()
+                        {
+                            self.theme = self.theme.toggled();
+                            apply_style(ctx, self.theme);
+                        }
+                        ui.toggle_value(&mut self.show_shortcuts, "Shortcuts")
+                            .on_hover_text("Keyboard shortcuts (?)");
+                    });
+                    // Why a vin_tables.toml on disk was ignored, for the whole session
+                    if let Some(ref error) = config::get().load_error {
+                        ui.colored_label(
+                            ui.visuals().warn_fg_color,
+                            format!("⚠ {} — using the bundled tables", error),
+                        );
+                    }
+                    if let Some(ref state) = self.undo {
+                        ui.horizontal(|ui| {
+                            ui.weak(format!("{}.", state.action));
+                            if ui
+                                .small_button("Undo")
+                                .on_hover_text(ctx.format_shortcut(&SHORTCUT_UNDO.keys))
+                                .clicked()
+                            {
+                                undo_request = true;
+                            }
+                        });
+                    }
                     ui.add_space(8.0);
 
                     // File Loading Section
-                    egui::Frame::new()
+                    panel_frame(ui)
                         .inner_margin(12.0)
                         .outer_margin(4.0)
-                        .corner_radius(2.0)
-                        .fill(egui::Color32::from_rgb(45, 45, 47))
-                        .stroke(egui::Stroke::new(
-                            3.0,
-                            egui::Color32::from_rgb(100, 100, 105),
-                        ))
                         .show(ui, |ui| {
                             ui.heading("⚙ File Loading");
                             ui.add_space(4.0);
@@ -759,10 +2741,39 @@ impl eframe::App for VinApp {
                                 "Drop carparts.txt onto the window to open or specify path below:",
                             );
                             ui.add_space(4.0);
-                            ui.add(
-                                egui::TextEdit::singleline(&mut self.file_path)
-                                    .desired_width(f32::INFINITY),
-                            );
+                            ui.horizontal(|ui| {
+                                if ui
+                                    .add(
+                                        egui::TextEdit::singleline(&mut self.file_path_text)
+                                            .desired_width(ui.available_width() - 56.0),
+                                    )
+                                    .changed()
+                                {
+                                    self.file_path = PathBuf::from(&self.file_path_text);
+                                }
+                                let reloadable =
+                                    matches!(self.last_source, LastSource::File) && !busy;
+                                if ui
+                                    .add_enabled(reloadable, egui::Button::new("↻").small())
+                                    .on_hover_text("Reload the loaded file from this path")
+                                    .on_disabled_hover_text("Load a file first")
+                                    .clicked()
+                                {
+                                    self.load_file(ctx);
+                                }
+                                if self.file_path_exists() {
+                                    ui.colored_label(ok_color(ui.visuals()), "✔")
+                                        .on_hover_text("File found");
+                                } else {
+                                    ui.colored_label(ui.visuals().error_fg_color, "✘")
+                                        .on_hover_text("No file at this path");
+                                }
+                            });
+                            if self.file_path.as_os_str().is_empty() {
+                                ui.weak(NO_PATH_HINT);
+                            } else if self.expanded_path != self.file_path {
+                                ui.weak(format!("→ {}", self.expanded_path.display()));
+                            }
                             ui.add_space(4.0);
                             ui.horizontal(|ui| {
                                 if ui
@@ -772,10 +2783,11 @@ impl eframe::App for VinApp {
                                 {
                                     if let Some(path) = rfd::FileDialog::new()
                                         .add_filter("Text files", &["txt"])
+                                        .add_filter("Gzip backups", &["gz"])
                                         .set_file_name("carparts.txt")
                                         .pick_file()
                                     {
-                                        self.file_path = path.display().to_string();
+                                        self.set_file_path(path);
                                     }
                                 }
                                 if ui
@@ -783,35 +2795,126 @@ impl eframe::App for VinApp {
                                     .on_hover_text("Reset to default path")
                                     .clicked()
                                 {
-                                    self.file_path = VinApp::default_file_path();
+                                    self.save_undo("Path reset", now);
+                                    self.set_file_path(VinApp::default_file_path());
                                     self.file_error = None;
                                 }
                                 if ui
-                                    .add(
+                                    .add_enabled(
+                                        !busy,
                                         egui::Button::new("Load")
-                                            .fill(egui::Color32::from_rgb(200, 120, 40)),
+                                            .fill(ui.visuals().selection.bg_fill),
                                     )
                                     .on_hover_text("Load VIN data from file")
                                     .clicked()
                                 {
-                                    let path = self.file_path.clone();
-                                    if !std::path::Path::new(&path).exists() {
-                                        self.file_error = Some(format!("File not found: {}", path));
-                                        self.vingen4_entries = None;
-                                    } else {
-                                        match parse_vingen4_file(&path) {
-                                            Some(entries) => {
-                                                self.vingen4_entries = Some(entries);
-                                                self.file_error = None;
-                                                self.last_source = LastSource::File;
+                                    self.load_file(ctx);
+                                }
+                                if busy {
+                                    ui.spinner();
+                                    if let Some(ref df) = self.queued_drop {
+                                        let name = df
+                                            .path
+                                            .as_ref()
+                                            .and_then(|path| path.file_name())
+                                            .map_or(df.name.clone(), |name| {
+                                                name.to_string_lossy().into_owned()
+                                            });
+                                        ui.weak(format!("Queued: {}", name))
+                                            .on_hover_text("Dropped during the load, loads next");
+                                    }
+                                }
+                                let mut watching = self.watch.is_some();
+                                if ui
+                                    .checkbox(&mut watching, "Watch")
+                                    .on_hover_text("Reload automatically whenever the game saves")
+                                    .changed()
+                                {
+                                    self.set_watching(watching, now);
+                                }
+                            });
+                            if self.vehicles.len() > 1 {
+                                ui.add_space(4.0);
+                                ui.horizontal(|ui| {
+                                    ui.label("Vehicle:");
+                                    let mut index = self.vehicle_index;
+                                    egui::ComboBox::from_id_salt("vehicle_select")
+                                        .selected_text(vehicle_label(
+                                            index,
+                                            &self.vehicles[index],
+                                        ))
+                                        .show_ui(ui, |ui| {
+                                            for (i, entries) in self.vehicles.iter().enumerate() {
+                                                ui.selectable_value(
+                                                    &mut index,
+                                                    i,
+                                                    vehicle_label(i, entries),
+                                                );
                                             }
-                                            None => {
-                                                self.file_error =
-                                                    Some("No VIN data found in file".to_string());
-                                                self.vingen4_entries = None;
+                                        })
+                                        .response
+                                        .on_hover_text("The save holds more than one VIN");
+                                    if index != self.vehicle_index {
+                                        self.vehicle_index = index;
+                                        self.vingen4_entries = Some(self.vehicles[index].clone());
+                                        self.last_source = LastSource::File;
+                                    }
+                                });
+                            }
+
+                            if let Some(diag) = self
+                                .diagnostics
+                                .as_ref()
+                                .filter(|d| self.file_error.is_none() && d.vin_tag.is_some())
+                            {
+                                ui.add_space(4.0);
+                                ui.collapsing("Format details", |ui| {
+                                    if let Some(ref tag) = diag.vin_tag {
+                                        ui.monospace(format!("Section: {}", tag));
+                                    }
+                                    for line in diag.detail_lines() {
+                                        ui.monospace(line);
+                                    }
+                                    if !diag.unknown_values.is_empty() {
+                                        ui.checkbox(
+                                            &mut self.show_number_readings,
+                                            "Read unknown values as numbers",
+                                        )
+                                        .on_hover_text(
+                                            "Show each 4-byte value as u32, i32 and f32 \
+                                             to help guess the real type",
+                                        );
+                                        if self.show_number_readings {
+                                            for line in diag.number_reading_lines() {
+                                                ui.monospace(line);
                                             }
                                         }
                                     }
+                                });
+                            }
+                            if self.diagnostics.as_ref().is_some_and(|d| !d.sections.is_empty()) {
+                                ui.add_space(4.0);
+                                ui.collapsing("Inspect sections", |ui| {
+                                    self.render_section_inspector(ui)
+                                });
+                            }
+                            ui.add_space(4.0);
+                            ui.collapsing("Paste hex dump", |ui| {
+                                ui.add(
+                                    egui::TextEdit::multiline(&mut self.hex_input)
+                                        .desired_width(f32::INFINITY)
+                                        .desired_rows(3)
+                                        .font(egui::TextStyle::Monospace)
+                                        .hint_text("7E 07 56 49 4E 47 65 6E 34 ..."),
+                                );
+                                if ui
+                                    .add_enabled(!busy, egui::Button::new("Decode hex"))
+                                    .on_hover_text(
+                                        "Decode a hex dump of a VINGen4 entry or its body",
+                                    )
+                                    .clicked()
+                                {
+                                    self.load_hex();
                                 }
                             });
 
@@ -820,12 +2923,9 @@ impl eframe::App for VinApp {
                                 egui::Frame::new()
                                     .inner_margin(8.0)
                                     .corner_radius(4.0)
-                                    .fill(egui::Color32::from_rgb(80, 20, 20))
+                                    .fill(error_fill(ui.visuals()))
                                     .show(ui, |ui| {
-                                        ui.colored_label(
-                                            egui::Color32::from_rgb(255, 100, 100),
-                                            err,
-                                        );
+                                        ui.colored_label(ui.visuals().error_fg_color, err);
                                     });
                             }
                         });
@@ -833,106 +2933,294 @@ impl eframe::App for VinApp {
                     ui.add_space(12.0);
 
                     // VIN Input Section
-                    egui::Frame::new()
+                    panel_frame(ui)
                         .inner_margin(12.0)
                         .outer_margin(4.0)
-                        .corner_radius(2.0)
-                        .fill(egui::Color32::from_rgb(45, 45, 47))
-                        .stroke(egui::Stroke::new(
-                            3.0,
-                            egui::Color32::from_rgb(100, 100, 105),
-                        ))
                         .show(ui, |ui| {
                             ui.heading("✏ Manual VIN Input");
                             ui.add_space(4.0);
-                            let vin_len: usize = VIN_STRUCTURE.iter().map(|f| f.len).sum();
-                            let mut decode_clicked = false;
-                            let vin_input_response = ui.add(
-                                egui::TextEdit::singleline(&mut self.vin_input)
-                                    .desired_width(f32::INFINITY)
-                                    .hint_text("Enter VIN code here..."),
-                            );
+                            ui.horizontal(|ui| {
+                                ui.label("Model:");
+                                let selected = self
+                                    .model_override
+                                    .map_or("Auto-detect", |i| VEHICLE_MODELS[i].name);
+                                egui::ComboBox::from_id_salt("model_select")
+                                    .selected_text(selected)
+                                    .show_ui(ui, |ui| {
+                                        ui.selectable_value(
+                                            &mut self.model_override,
+                                            None,
+                                            "Auto-detect",
+                                        );
+                                        for (i, model) in VEHICLE_MODELS.iter().enumerate() {
+                                            ui.selectable_value(
+                                                &mut self.model_override,
+                                                Some(i),
+                                                model.name,
+                                            );
+                                        }
+                                    });
+                                ui.checkbox(&mut self.case_sensitive_codes, "Case-sensitive codes")
+                                    .on_hover_text(
+                                        "Look codes up with their exact case instead of \
+                                         uppercasing manual input and loaded values",
+                                    );
+                            });
                             ui.add_space(4.0);
-                            if ui
+                            let mut decode_clicked = false;
+                            let input_id = egui::Id::new("vin_input");
+                            self.recall_history(ui, input_id);
+                            let vin_input_response = ui
                                 .add(
-                                    egui::Button::new("Decode")
-                                        .fill(egui::Color32::from_rgb(200, 120, 40)),
+                                    egui::TextEdit::singleline(&mut self.vin_input)
+                                        .id(input_id)
+                                        .desired_width(f32::INFINITY)
+                                        .hint_text("Enter VIN code here..."),
                                 )
-                                .on_hover_text("Decode the entered VIN")
-                                .clicked()
-                            {
-                                decode_clicked = true;
-                            }
-                            if (vin_input_response.lost_focus()
-                                && ui.input(|i| i.key_pressed(egui::Key::Enter)))
-                                || decode_clicked
-                            {
-                                let vin = self.vin_input.trim().replace(' ', "").to_uppercase();
-                                if vin.len() != vin_len {
-                                    self.vin_error = Some(format!(
-                                        "Invalid VIN length: {} characters (expected {})",
-                                        vin.len(),
-                                        vin_len
-                                    ));
-                                    self.entries = None;
-                                } else {
-                                    self.entries = Some(parse_vin(&vin));
-                                    self.vin_error = None;
+                                .on_hover_text("Up/Down recall earlier VINs");
+                            ui.add_space(4.0);
+                            ui.horizontal(|ui| {
+                                if ui
+                                    .add_enabled(
+                                        !busy,
+                                        egui::Button::new("Decode")
+                                            .fill(ui.visuals().selection.bg_fill),
+                                    )
+                                    .on_hover_text("Decode the entered VIN")
+                                    .clicked()
+                                {
+                                    decode_clicked = true;
+                                }
+                                if ui
+                                    .add_enabled(!busy, egui::Button::new("Scan text"))
+                                    .on_hover_text(
+                                        "Find and decode a VIN inside pasted text, e.g. a forum post",
+                                    )
+                                    .clicked()
+                                {
+                                    self.scan_input();
+                                }
+                                if ui
+                                    .add_enabled(
+                                        !busy && self.vingen4_entries.is_some(),
+                                        egui::Button::new("Validate against loaded file"),
+                                    )
+                                    .on_hover_text(
+                                        "Compare the typed VIN field by field with the loaded car",
+                                    )
+                                    .clicked()
+                                {
+                                    self.validate_against_file();
+                                }
+                                if ui
+                                    .button("Clear")
+                                    .on_hover_text("Clear the VIN and all results")
+                                    .clicked()
+                                {
+                                    self.clear(now);
                                 }
-                                self.last_source = LastSource::Vin;
+                            });
+                            let enter_pressed = vin_input_response.lost_focus()
+                                && ui.input(|i| i.key_pressed(SHORTCUT_DECODE.keys.logical_key));
+                            if (enter_pressed && !busy) || decode_clicked {
+                                self.decode_input();
                             }
+                            if let Some(preview) = self.input_preview() {
+                                ui.weak(format!("Reads as: {}", preview))
+                                    .on_hover_text("The VIN as it will be decoded");
+                            }
+                            if let Some(hint) = self.vin_hint {
+                                ui.add_space(4.0);
+                                ui.weak(hint);
+                            }
+                            self.render_validation(ui);
+                            ui.add_space(4.0);
+                            ui.collapsing("Build a VIN", |ui| self.render_builder(ui));
+                            ui.collapsing("Search options", |ui| self.render_option_search(ui));
+                            ui.collapsing("Step by step", |ui| self.render_wizard(ui));
+                            ui.collapsing("Compare two VINs", |ui| self.render_compare(ui));
                         });
 
                     ui.add_space(8.0);
                     ui.separator();
                     ui.add_space(8.0);
 
-                    match self.last_source {
-                        LastSource::File => {
-                            if let Some(ref entries) = self.vingen4_entries {
-                                let entry_map: HashMap<_, _> = entries
-                                    .iter()
-                                    .map(|(k, v)| {
-                                        let val = if v.starts_with("string(") && v.ends_with(")") {
-                                            &v[7..v.len() - 1]
-                                        } else {
-                                            &**v
-                                        };
-                                        (&**k, val)
-                                    })
-                                    .collect();
-                                render_vin_table(ui, &self.decode_map, |key| {
-                                    entry_map.get(key).copied().unwrap_or(EMPTY)
+                    // The live view only hides the serial while a screenshot is captured
+                    let redact = self.redact_serial && self.pending_screenshot.is_some();
+                    if matches!(self.last_source, LastSource::Vin) {
+                        if let Some(ref err) = self.vin_error {
+                            ui.add_space(8.0);
+                            egui::Frame::new()
+                                .inner_margin(8.0)
+                                .corner_radius(4.0)
+                                .fill(error_fill(ui.visuals()))
+                                .show(ui, |ui| {
+                                    ui.vertical_centered(|ui| {
+                                        ui.colored_label(ui.visuals().error_fg_color, err);
+                                    });
                                 });
-                            }
+                            ui.add_space(4.0);
+                            self.render_alignments(ui);
+                            ui.add_space(8.0);
                         }
-                        LastSource::Vin => {
-                            if let Some(ref err) = self.vin_error {
-                                ui.add_space(8.0);
-                                egui::Frame::new()
-                                    .inner_margin(8.0)
-                                    .corner_radius(4.0)
-                                    .fill(egui::Color32::from_rgb(80, 20, 20))
-                                    .show(ui, |ui| {
-                                        ui.vertical_centered(|ui| {
-                                            ui.colored_label(
-                                                egui::Color32::from_rgb(255, 100, 100),
-                                                err,
+                    }
+                    if self.auto_loaded && matches!(self.last_source, LastSource::File) {
+                        ui.vertical_centered(|ui| ui.weak(AUTO_LOAD_NOTE));
+                        ui.add_space(4.0);
+                    }
+                    if let Some((file, typed)) = self.split_decoded(redact) {
+                        let mut rects = Vec::new();
+                        ui.columns(2, |columns| {
+                            let sides = [("From file", &file), ("Typed VIN", &typed)];
+                            for (ui, (title, decoded)) in columns.iter_mut().zip(sides) {
+                                ui.vertical_centered(|ui| ui.strong(title));
+                                rects.push(self.render_results(ui, &mut table_sort, decoded));
+                            }
+                        });
+                        results_rect = rects.into_iter().reduce(egui::Rect::union);
+                    } else if let Some(decoded) = self.current_decoded(redact) {
+                        results_rect = Some(self.render_results(ui, &mut table_sort, &decoded));
+                    } else if !busy && self.vin_error.is_none() && self.file_error.is_none() {
+                        render_welcome(ui);
+                    }
+
+                    if self.current_values().is_some() {
+                        ui.add_space(8.0);
+                        ui.vertical_centered(|ui| {
+                            ui.add(
+                                egui::TextEdit::multiline(&mut self.notes)
+                                    .hint_text("Notes, e.g. project car, needs paint")
+                                    .desired_rows(2)
+                                    .desired_width(300.0),
+                            )
+                            .on_hover_text("Included in the Text and JSON exports");
+                            ui.add_space(4.0);
+                            for format in ExportFormat::ALL {
+                                if ui
+                                    .button(format!("Export {}", format.name()))
+                                    .on_hover_text(format!(
+                                        "Save the decode to a file ({})",
+                                        ctx.format_shortcut(&SHORTCUT_REPEAT_EXPORT.keys)
+                                    ))
+                                    .clicked()
+                                {
+                                    export_request = Some(format);
+                                }
+                            }
+                            ui.horizontal(|ui| {
+                                if ui
+                                    .button("Copy wiki table")
+                                    .on_hover_text(
+                                        "Copy the decode as a MediaWiki table for the community wiki",
+                                    )
+                                    .clicked()
+                                {
+                                    if let Some(decoded) = self.decoded_for_export() {
+                                        ctx.copy_text(ExportFormat::Wiki.render(&decoded));
+                                    }
+                                }
+                                if ui
+                                    .button("Copy key=value")
+                                    .on_hover_text("Copy one Field=Decoded line per field")
+                                    .clicked()
+                                {
+                                    if let Some(decoded) = self.decoded_for_export() {
+                                        ctx.copy_text(ExportFormat::KeyValue.render(&decoded));
+                                    }
+                                }
+                                if ui
+                                    .button("Copy image")
+                                    .on_hover_text("Copy a picture of the results to the clipboard")
+                                    .clicked()
+                                {
+                                    screenshot_request = Some(ScreenshotTarget::Clipboard);
+                                }
+                                if ui
+                                    .button("Save image...")
+                                    .on_hover_text("Save a picture of the results as PNG")
+                                    .clicked()
+                                {
+                                    screenshot_request = Some(ScreenshotTarget::File);
+                                }
+                            });
+                            if ui
+                                .button("📌 Pin")
+                                .on_hover_text(
+                                    "Keep this decode in a side column to compare later ones against",
+                                )
+                                .clicked()
+                            {
+                                self.pinned = self.current_decoded(false);
+                            }
+                            if ui
+                                .button("Export all...")
+                                .on_hover_text(
+                                    "Save every export format and a PNG into one folder",
+                                )
+                                .clicked()
+                            {
+                                bundle_request = true;
+                            }
+                            ui.checkbox(&mut self.decoded_only, "Decoded values only")
+                                .on_hover_text(
+                                    "Hide the raw codes; the complete VIN still shows them",
+                                );
+                            ui.horizontal(|ui| {
+                                ui.label("Serial:");
+                                egui::ComboBox::from_id_salt("serial_display")
+                                    .selected_text(self.serial_display.name())
+                                    .show_ui(ui, |ui| {
+                                        for display in SerialDisplay::ALL {
+                                            ui.selectable_value(
+                                                &mut self.serial_display,
+                                                display,
+                                                display.name(),
                                             );
-                                        });
+                                        }
                                     });
-                                ui.add_space(8.0);
+                            });
+                            ui.checkbox(&mut self.plant_locations, "Plant locations")
+                                .on_hover_text(
+                                    "Show the country after the assembly plant, e.g. \"Rheine, West Germany\"",
+                                );
+                            if self.vingen4_entries.is_some() && self.entries.is_some() {
+                                ui.checkbox(&mut self.split_view, "Side by side").on_hover_text(
+                                    "Show the file and the typed VIN next to each other; \
+                                     exports use the latest of the two",
+                                );
                             }
-                            if let Some(ref entries) = self.entries {
-                                render_vin_table(ui, &self.decode_map, |key| {
-                                    entries.get(key).map_or(EMPTY, |s| s)
-                                });
+                            ui.checkbox(&mut self.show_positions, "Positions")
+                                .on_hover_text(
+                                    "Show each field's character range, e.g. 8-12 for the serial",
+                                );
+                            ui.checkbox(&mut self.redact_serial, "Redact serial")
+                                .on_hover_text(
+                                    "Replace the serial with XXXXX in exports and images",
+                                );
+                            if let Some(ref status) = self.export_status {
+                                ui.small(status);
                             }
-                        }
-                        LastSource::None => {}
+                        });
                     }
                 });
         });
+
+        self.results_rect = results_rect;
+        self.table_sort = table_sort;
+        if undo_request {
+            self.undo();
+        }
+        if let Some(format) = export_request {
+            self.export(format);
+        }
+        if bundle_request {
+            screenshot_request = self.export_bundle();
+        }
+        if let Some(target) = screenshot_request {
+            self.pending_screenshot = Some(target);
+            ctx.request_repaint();
+            ctx.send_viewport_cmd(egui::ViewportCommand::Screenshot(egui::UserData::default()));
+        }
     }
 }
 
@@ -958,8 +3246,26 @@ fn load_icon() -> Option<egui::IconData> {
 
 /// Entry point
 fn main() {
-    let initial_size = egui::vec2(520.0, 960.0);
-    let min_size = egui::vec2(520.0, 960.0);
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    cli::take_console_flag(&mut args);
+    // Opened through a decode link: start the GUI on that VIN
+    let linked = args
+        .first()
+        .and_then(|arg| vin_from_deep_link(arg))
+        .map(str::to_string);
+    if linked.is_none() {
+        if let Some(code) = cli::run(args.into_iter()) {
+            std::process::exit(code);
+        }
+    }
+
+    let settings = settings::load();
+    let min_size = egui::vec2(280.0, 480.0);
+    let initial_size = settings
+        .window_size
+        .map_or(egui::vec2(520.0, 960.0), |[w, h]| {
+            egui::vec2(w, h).max(min_size)
+        });
 
     // Load icon
     let icon_data = load_icon();
@@ -968,6 +3274,9 @@ fn main() {
         .with_inner_size(initial_size)
         .with_min_inner_size(min_size)
         .with_resizable(true);
+    if let Some([x, y]) = settings.window_pos {
+        viewport_builder = viewport_builder.with_position(egui::pos2(x, y));
+    }
 
     if let Some(icon) = icon_data {
         viewport_builder = viewport_builder.with_icon(icon);
@@ -982,7 +3291,285 @@ fn main() {
     eframe::run_native(
         &title,
         options,
-        Box::new(|_cc| Ok(Box::new(VinApp::default()))),
+        Box::new(|cc| {
+            let mut app = VinApp::default();
+            app.restore_settings(&settings);
+            if let Some(vin) = linked {
+                app.vin_input = vin;
+                app.decode_input();
+            } else {
+                app.start_auto_load(&cc.egui_ctx);
+            }
+            Ok(Box::new(app))
+        }),
     )
     .expect("Failed to start eframe application");
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use mwc_vin_decoder::{
+        check_combinations, decode_map, decode_vin, parse_vin, split_vin_entries, VIN_STRUCTURE,
+    };
+
+    use super::*;
+
+    /// VIN of the bundled example_carparts.txt
+    const CANONICAL_VIN: &str = "UCBBGNY442711NEBSBKCNJM28A-4B";
+
+    #[test]
+    fn vehicle_labels() {
+        let first = split_vin_entries(CANONICAL_VIN);
+        let mut second = first.clone();
+        if let Some(model) = second.iter_mut().find(|(k, _)| k == "Model") {
+            model.1 = "Z".to_string();
+        }
+        assert!(
+            vehicle_label(0, &first) == "Vehicle 1 (Rivett)",
+            "vehicle label names the model: {}",
+            vehicle_label(0, &first),
+        );
+        assert!(
+            vehicle_label(1, &second) == "Vehicle 2",
+            "vehicle label without a known model: {}",
+            vehicle_label(1, &second),
+        );
+    }
+
+    #[test]
+    fn env_expansion() {
+        std::env::set_var("MWC_SELFTEST_DIR", "C:\\Users\\me");
+        for (input, expected) in [
+            (
+                "%MWC_SELFTEST_DIR%\\carparts.txt",
+                "C:\\Users\\me\\carparts.txt",
+            ),
+            (
+                "$MWC_SELFTEST_DIR/carparts.txt",
+                "C:\\Users\\me/carparts.txt",
+            ),
+            ("${MWC_SELFTEST_DIR}x", "C:\\Users\\mex"),
+            ("%MWC_SELFTEST_UNSET%\\a", "%MWC_SELFTEST_UNSET%\\a"),
+            ("100% $5 ${", "100% $5 ${"),
+            ("C:\\plain\\carparts.txt", "C:\\plain\\carparts.txt"),
+        ] {
+            let expanded = expand_env_vars(input);
+            assert!(expanded == expected, "expand {}: got {:?}", input, expanded,);
+        }
+        let expanded = expand_path(Path::new("%MWC_SELFTEST_DIR%\\carparts.txt"));
+        assert!(
+            expanded.to_str() == Some("C:\\Users\\me\\carparts.txt"),
+            "expand path: got {:?}",
+            expanded,
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let raw = std::ffi::OsStr::from_bytes(b"/tmp/%MWC_SELFTEST_DIR%/car\xFFparts.txt");
+            let expanded = expand_path(Path::new(raw));
+            assert!(
+                expanded.as_os_str() == raw,
+                "non-UTF-8 path kept: got {:?}",
+                expanded,
+            );
+        }
+    }
+
+    #[test]
+    fn months_in_calendar_order() {
+        let months = month_options(&decode_map());
+        let codes: String = months.iter().map(|(code, _)| *code).collect();
+        assert!(
+            codes == "CKDELYSTJUMP",
+            "months in calendar order: got {:?}",
+            codes,
+        );
+        let february = months.get(1).map(|(_, label)| label.as_str());
+        assert!(
+            february == Some("02 February"),
+            "month label: got {:?}",
+            february,
+        );
+    }
+
+    #[test]
+    fn mismatched_fields() {
+        let file = parse_vin(CANONICAL_VIN, VIN_STRUCTURE);
+        let file_value = |key: &str| file.get(key).map_or("", String::as_str);
+        let same = field_mismatches(VIN_STRUCTURE, file_value, file_value);
+        assert!(
+            same.is_empty(),
+            "identical VINs match: {} mismatches",
+            same.len(),
+        );
+        let typed = parse_vin(&CANONICAL_VIN.replacen('G', "E", 1), VIN_STRUCTURE);
+        let differ = field_mismatches(VIN_STRUCTURE, file_value, |key| {
+            typed.get(key).map_or("", String::as_str)
+        });
+        let fields: Vec<_> = differ
+            .iter()
+            .map(|m| (m.field, m.file.as_str(), m.typed.as_str()))
+            .collect();
+        assert!(
+            fields == [("Version", "G", "E")],
+            "mismatched field listed: got {:?}",
+            fields,
+        );
+    }
+
+    #[test]
+    fn changed_fields_logged() {
+        let map = decode_map();
+        let before = parse_vin(CANONICAL_VIN, VIN_STRUCTURE);
+        let after = parse_vin(&CANONICAL_VIN.replacen('G', "P", 1), VIN_STRUCTURE);
+        let changes = field_changes(
+            VIN_STRUCTURE,
+            &map,
+            |key| before.get(key).map_or("", String::as_str),
+            |key| after.get(key).map_or("", String::as_str),
+            std::time::UNIX_EPOCH,
+        );
+        let listed: Vec<_> = changes
+            .iter()
+            .map(|c| (c.field, c.old.as_str(), c.new.as_str()))
+            .collect();
+        assert!(
+            listed == [("Version", "G (SLX)", "P (GT)")],
+            "changed field logged: got {:?}",
+            listed,
+        );
+        let at = std::time::UNIX_EPOCH + std::time::Duration::from_secs(86_400 + 3_723);
+        assert!(
+            clock_time(at) == "01:02:03",
+            "clock time: got {}",
+            clock_time(at),
+        );
+    }
+
+    #[test]
+    fn serial_display() {
+        let serial = VIN_STRUCTURE.iter().find(|f| f.key == "Serial");
+        let engine = VIN_STRUCTURE.iter().find(|f| f.key == "Engine");
+        for (field, display, val, expected) in [
+            (serial, SerialDisplay::Grouped, "12345", "12,345"),
+            (serial, SerialDisplay::Grouped, "00999", "999"),
+            (serial, SerialDisplay::Grouped, "1234567", "1,234,567"),
+            (serial, SerialDisplay::Padded, "1234", "01234"),
+            (serial, SerialDisplay::Padded, "44271", "44271"),
+            (serial, SerialDisplay::AsEntered, "01234", "01234"),
+            (serial, SerialDisplay::Grouped, "XXXXX", "XXXXX"),
+            (engine, SerialDisplay::Padded, "NE", "NE"),
+        ] {
+            let shown = field.map(|f| display.format(f, val));
+            assert!(
+                shown.as_deref() == Some(expected),
+                "serial display {}: expected {:?}, got {:?}",
+                val,
+                expected,
+                shown,
+            );
+        }
+    }
+
+    #[test]
+    fn drivetrain() {
+        let map = decode_map();
+        let fields = parse_vin(CANONICAL_VIN, VIN_STRUCTURE);
+        let decoded = DecodedVin::new(VIN_STRUCTURE, &map, |key| {
+            fields.get(key).map_or("", String::as_str)
+        });
+        let note = drivetrain_note(&decoded);
+        let expected =
+        "Drivetrain: rear-wheel drive through a live rear axle, 3.44 axle ratio, LSD rear differential";
+        assert!(
+            note.as_deref() == Some(expected),
+            "drivetrain note: got {:?}",
+            note,
+        );
+
+        let mut awd = fields.clone();
+        awd.insert("Drive".to_string(), "4".to_string());
+        awd.insert("AxleLock".to_string(), "B".to_string());
+        let decoded = DecodedVin::new(VIN_STRUCTURE, &map, |key| {
+            awd.get(key).map_or("", String::as_str)
+        });
+        let note = drivetrain_note(&decoded);
+        assert!(
+            note.as_deref()
+                .is_some_and(|n| n.starts_with("Unknown drive code \"4\"")),
+            "unknown drive code noted: got {:?}",
+            note,
+        );
+        let warnings = check_combinations(|key| awd.get(key).map_or("", String::as_str));
+        let expected = [
+            "Axle ratios were only listed for the rear-wheel-drive axle",
+            "The LSD was only built into the rear-wheel-drive axle",
+        ];
+        assert!(
+            warnings.ends_with(&expected),
+            "axle options need RWD: got {:?}",
+            warnings,
+        );
+    }
+
+    #[test]
+    fn field_position_labels() {
+        let position = |key: &str| {
+            let field = VIN_STRUCTURE.iter().find(|f| f.key == key).unwrap();
+            field_position(VIN_STRUCTURE, field)
+        };
+        let got = [
+            position("Country"),
+            position("Serial"),
+            position("WindowHeater"),
+        ];
+        let last = VIN_STRUCTURE
+            .iter()
+            .map(|f| f.len)
+            .sum::<usize>()
+            .to_string();
+        assert!(
+            got[0] == "1" && got[1] == "8-12" && got[2] == last,
+            "field positions: got {:?}",
+            got,
+        );
+    }
+
+    #[test]
+    fn theme_toggle() {
+        assert!(
+            Theme::Workshop.toggled() == Theme::Light && Theme::Light.toggled() == Theme::Workshop,
+            "theme toggles back and forth: got {:?}",
+            Theme::Workshop.toggled(),
+        );
+        let dark = Theme::Workshop.visuals();
+        let light = Theme::Light.visuals();
+        assert!(
+            dark.dark_mode && !light.dark_mode && ok_color(&dark) != ok_color(&light),
+            "light theme is bright: panel fills {:?} and {:?}",
+            dark.panel_fill,
+            light.panel_fill,
+        );
+    }
+
+    #[test]
+    fn vin_compare() {
+        let map = decode_map();
+        let a = decode_vin(CANONICAL_VIN, &map);
+        let b = decode_vin(&CANONICAL_VIN.replacen("UCBBG", "UCBBP", 1), &map);
+        let rows = compare_rows(&a, &b);
+        let differing: Vec<_> = rows
+            .iter()
+            .filter(|row| row.differs())
+            .map(|row| (row.field.key, row.sides))
+            .collect();
+        assert!(
+            rows.len() == VIN_STRUCTURE.len()
+                && differing == [("Version", [("G", "SLX"), ("P", "GT")])],
+            "compare marks only the changed field: got {:?}",
+            differing,
+        );
+    }
+}
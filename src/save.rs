@@ -0,0 +1,66 @@
+//! File dialogs and screenshot saving for the exports
+
+use std::path::{Path, PathBuf};
+
+use mwc_vin_decoder::export::ExportFormat;
+
+/// Ask for a target file and write `contents` to it, returns the written path
+/// or `None` when the dialog was cancelled
+pub fn save_with_dialog(format: ExportFormat, contents: &str) -> Result<Option<PathBuf>, String> {
+    save_text_with_dialog(
+        format.name(),
+        format.extension(),
+        &format!("vin.{}", format.extension()),
+        contents,
+    )
+}
+
+/// Ask for a target file of one type and write `contents` to it
+pub fn save_text_with_dialog(
+    filter_name: &str,
+    extension: &str,
+    file_name: &str,
+    contents: &str,
+) -> Result<Option<PathBuf>, String> {
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter(filter_name, &[extension])
+        .set_file_name(file_name)
+        .save_file()
+    else {
+        return Ok(None);
+    };
+    std::fs::write(&path, contents)
+        .map(|_| Some(path.clone()))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Ask for a target file and save a captured image as PNG
+pub fn save_png_with_dialog(image: &egui::ColorImage) -> Result<Option<PathBuf>, String> {
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter("PNG image", &["png"])
+        .set_file_name("vin.png")
+        .save_file()
+    else {
+        return Ok(None);
+    };
+    save_png(image, &path).map(|_| Some(path))
+}
+
+/// Save a captured image as PNG
+pub fn save_png(image: &egui::ColorImage, path: &Path) -> Result<(), String> {
+    let [width, height] = image.size;
+    let rgba: Vec<u8> = image.pixels.iter().flat_map(|c| c.to_array()).collect();
+    let buffer = image::RgbaImage::from_raw(width as u32, height as u32, rgba)
+        .ok_or_else(|| "Captured image has an unexpected size".to_string())?;
+    buffer
+        .save(path)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Ask for the folder and common base name of an export bundle
+pub fn pick_bundle_base() -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .set_title("Export all as")
+        .set_file_name("vin")
+        .save_file()
+}
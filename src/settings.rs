@@ -0,0 +1,111 @@
+//! Preferences kept between runs (`settings.toml` in the user's config folder)
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Theme;
+
+/// Settings file name inside [`SETTINGS_DIR`]
+pub const SETTINGS_FILE: &str = "settings.toml";
+
+/// Folder of the settings file inside the user's config folder
+const SETTINGS_DIR: &str = "mwc-vin-decoder";
+
+/// Contents of `settings.toml`; every entry is optional so older or
+/// hand-edited files still load
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Settings {
+    /// carparts.txt that was last loaded successfully
+    #[serde(default)]
+    pub last_path: Option<PathBuf>,
+    #[serde(default)]
+    pub theme: Theme,
+    /// Inner window size in points
+    #[serde(default)]
+    pub window_size: Option<[f32; 2]>,
+    /// Outer window position in points
+    #[serde(default)]
+    pub window_pos: Option<[f32; 2]>,
+}
+
+impl Settings {
+    /// `last_path` when the file is still there
+    pub fn existing_path(&self) -> Option<&PathBuf> {
+        self.last_path.as_ref().filter(|path| path.is_file())
+    }
+}
+
+/// Where the settings live: `%APPDATA%` on Windows, the XDG config folder elsewhere
+fn settings_path() -> Option<PathBuf> {
+    let dir = std::env::var_os("APPDATA")
+        .or_else(|| std::env::var_os("XDG_CONFIG_HOME"))
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(dir.join(SETTINGS_DIR).join(SETTINGS_FILE))
+}
+
+/// Read the saved settings; missing or unreadable files give the defaults
+pub fn load() -> Settings {
+    let Some(path) = settings_path() else {
+        return Settings::default();
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(text) => parse(&text).unwrap_or_else(|e| {
+            eprintln!("Ignoring {}: {}", path.display(), e);
+            Settings::default()
+        }),
+        Err(_) => Settings::default(),
+    }
+}
+
+/// Parse the text of a settings file
+pub fn parse(text: &str) -> Result<Settings, String> {
+    toml::from_str(text).map_err(|e| e.to_string())
+}
+
+/// Text of a settings file for `settings`
+pub fn render(settings: &Settings) -> Result<String, String> {
+    toml::to_string(settings).map_err(|e| e.to_string())
+}
+
+/// Write `settings`, creating the folder when needed
+pub fn save(settings: &Settings) -> Result<(), String> {
+    let path = settings_path().ok_or("No config folder to save settings in")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    std::fs::write(&path, render(settings)?).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let saved = Settings {
+            last_path: Some(PathBuf::from("saves/carparts.txt")),
+            theme: Theme::Light,
+            window_size: Some([600.0, 900.0]),
+            window_pos: Some([10.0, 20.0]),
+        };
+        let parsed = render(&saved).and_then(|text| parse(&text));
+        assert!(
+            parsed.as_ref() == Ok(&saved),
+            "settings round trip: got {:?}",
+            parsed,
+        );
+        let empty = parse("");
+        assert!(
+            empty.as_ref() == Ok(&Settings::default()),
+            "empty settings file gives the defaults: got {:?}",
+            empty,
+        );
+        assert!(
+            saved.existing_path().is_none(),
+            "vanished last path is not restored: got {:?}",
+            saved.existing_path(),
+        );
+    }
+}
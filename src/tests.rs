@@ -0,0 +1,1211 @@
+//! Tests of the save parser and decoder
+//!
+//! Builds synthetic carparts.txt buffers (see [`crate::writer`] for the
+//! layout) for each supported value type and checks that the parser reads
+//! them back, then decodes known VINs.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::codes::{Drive, Engine, FieldCode, Gearbox, Version, Year};
+use crate::config::{validate, VinConfig, DEFAULT_CONFIG};
+use crate::decoded::{
+    field_status, share_code, suggest_code, DecodeSource, DecodedVin, DecodedVinBuilder,
+    FieldStatus,
+};
+use crate::{
+    alignments, check_combinations, decode_confidence, decode_map, decode_vin, decoded_fields,
+    deep_link, encode_field, export, find_vin_in_text, format_float, guard_parse,
+    gunzip_if_compressed, looks_like_carparts, normalize_code, parse_carparts, parse_delimited_vin,
+    parse_dictionary_vec, parse_section, parse_value, parse_vin, parse_vingen4_bytes,
+    parse_vingen4_file, read_header, round_typed_float, search_options, section_order,
+    split_vin_entries, vin_from_deep_link, vin_tokens, writer, DecodeMap, ParseDiagnostics,
+    HX_START_ENTRY, PARSE_PANIC_ERROR, REDACTED_SERIAL, VALUE_TYPE_BOOL, VALUE_TYPE_INT32,
+    VALUE_TYPE_STRING, VEHICLE_MODELS, VIN_STRUCTURE,
+};
+
+/// VIN of the bundled example_carparts.txt
+const CANONICAL_VIN: &str = "UCBBGNY442711NEBSBKCNJM28A-4B";
+
+/// Parse a single VINGen4 entry with the given body
+fn parse_body(body: &[u8]) -> Option<Vec<(String, String)>> {
+    let buffer = writer::entry(writer::VINGEN4_TAG, body);
+    parse_vingen4_bytes(&buffer, &mut ParseDiagnostics::default())
+}
+
+/// Owned pairs for comparisons
+fn pairs(items: &[(&str, &str)]) -> Vec<(String, String)> {
+    items
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[test]
+fn value_types() {
+    let mut payload = Vec::new();
+    for (k, v) in [("Engine", "string(NE)"), ("Serial", "string(44271)")] {
+        payload.extend(writer::string(k));
+        payload.extend(writer::string(v));
+    }
+    let parsed = parse_body(&writer::dictionary(VALUE_TYPE_STRING, 2, &payload));
+    let expected = pairs(&[("Engine", "string(NE)"), ("Serial", "string(44271)")]);
+    assert!(
+        parsed.as_ref() == Some(&expected),
+        "string values: got {:?}",
+        parsed,
+    );
+
+    let mut payload = Vec::new();
+    for (k, v) in [("a", 5i32), ("b", -1)] {
+        payload.extend(writer::string(k));
+        payload.extend(v.to_le_bytes());
+    }
+    payload.extend(writer::string("Serial"));
+    payload.extend(0xFFFF_FFFFu32.to_le_bytes());
+    let parsed = parse_body(&writer::dictionary(VALUE_TYPE_INT32, 3, &payload));
+    // Serial is unsigned, other keys keep the signed reading
+    let expected = pairs(&[("a", "5"), ("b", "-1"), ("Serial", "4294967295")]);
+    assert!(
+        parsed.as_ref() == Some(&expected),
+        "int32 values: got {:?}",
+        parsed,
+    );
+    let mut diagnostics = ParseDiagnostics::default();
+    let body = writer::dictionary(VALUE_TYPE_INT32, 3, &payload);
+    parse_vingen4_bytes(&writer::entry(writer::VINGEN4_TAG, &body), &mut diagnostics);
+    let lines = diagnostics.detail_lines();
+    assert!(
+        lines
+            .iter()
+            .any(|l| l == "b: -1 signed / 4294967295 unsigned"),
+        "int32 signed and unsigned in diagnostics: got {:?}",
+        lines,
+    );
+
+    let mut payload = Vec::new();
+    for (k, v) in [("on", 1u8), ("off", 0)] {
+        payload.extend(writer::string(k));
+        payload.push(v);
+    }
+    let parsed = parse_body(&writer::dictionary(VALUE_TYPE_BOOL, 2, &payload));
+    let expected = pairs(&[("on", "true"), ("off", "false")]);
+    assert!(
+        parsed.as_ref() == Some(&expected),
+        "bool values: got {:?}",
+        parsed,
+    );
+
+    let mut payload = writer::string("speed");
+    payload.extend(1.5f32.to_le_bytes());
+    let mut diagnostics = ParseDiagnostics::default();
+    let body = writer::dictionary(0x1234_5678, 1, &payload);
+    parse_vingen4_bytes(&writer::entry(writer::VINGEN4_TAG, &body), &mut diagnostics);
+    let lines = diagnostics.number_reading_lines();
+    assert!(
+        lines.get(1).is_some_and(|l| {
+            l.split_whitespace()
+                .eq(["speed", "0000c03f", "1069547520", "1069547520", "1.5e0"])
+        }),
+        "unknown value read as numbers: got {:?}",
+        lines,
+    );
+}
+
+#[test]
+fn round_trip() {
+    let entries = split_vin_entries(CANONICAL_VIN);
+    let parsed = parse_vingen4_bytes(&writer::vingen4(&entries), &mut ParseDiagnostics::default());
+    assert!(
+        parsed.as_ref() == Some(&entries),
+        "writer round trip: got {:?}",
+        parsed,
+    );
+}
+
+#[test]
+fn multiple_vehicles() {
+    let first = split_vin_entries(CANONICAL_VIN);
+    let mut second = first.clone();
+    if let Some(model) = second.iter_mut().find(|(k, _)| k == "Model") {
+        model.1 = "Z".to_string();
+    }
+    let mut save = writer::vingen4(&first);
+    save.extend(writer::vingen4(&second));
+    let (result, _) = parse_carparts(save, "carparts.txt");
+    assert!(
+        result.as_ref() == Ok(&vec![first, second]),
+        "every VINGen4 section loaded in order: got {:?}",
+        result,
+    );
+}
+
+#[test]
+fn carparts_sniffing() {
+    let save = writer::vingen4(&split_vin_entries(CANONICAL_VIN));
+    assert!(
+        looks_like_carparts(&save),
+        "save recognized: writer output rejected",
+    );
+    let mut late = vec![HX_START_ENTRY, 200];
+    late.extend(&save);
+    assert!(
+        looks_like_carparts(&late),
+        "save recognized after a corrupt entry: rejected",
+    );
+    let other = writer::entry("FANBELT0", &[1, 2, 3]);
+    for (name, buffer) in [
+        ("text file", &b"Hello ~ world, this is not a save"[..]),
+        ("unknown sections only", &other[..]),
+        ("empty file", &[][..]),
+    ] {
+        assert!(
+            !looks_like_carparts(buffer),
+            "{}: accepted as carparts.txt",
+            name,
+        );
+    }
+}
+
+#[test]
+fn long_string() {
+    let long = "x".repeat(300);
+    let mut payload = writer::string("Long");
+    payload.extend(writer::string(&long));
+    let parsed = parse_body(&writer::dictionary(VALUE_TYPE_STRING, 1, &payload));
+    let expected = vec![("Long".to_string(), long)];
+    assert!(
+        parsed.as_ref() == Some(&expected),
+        "300-byte string: got {:?}",
+        parsed.map(|p| p.len()),
+    );
+}
+
+#[test]
+fn truncated_string() {
+    let mut payload = writer::string("Engine");
+    payload.extend(writer::string("string(NE)"));
+    payload.extend(writer::string("Serial"));
+    // Claims 13 bytes, only 5 follow
+    payload.push(13);
+    payload.extend_from_slice(b"strin");
+    let body = writer::dictionary(VALUE_TYPE_STRING, 3, &payload);
+    let mut diagnostics = ParseDiagnostics::default();
+    let parsed = parse_vingen4_bytes(&writer::entry(writer::VINGEN4_TAG, &body), &mut diagnostics);
+    let expected = pairs(&[("Engine", "string(NE)"), ("Serial", "strin")]);
+    assert!(
+        parsed.as_ref() == Some(&expected),
+        "truncated final string: got {:?}",
+        parsed,
+    );
+    let lines = diagnostics.detail_lines();
+    assert!(
+        lines
+            .iter()
+            .any(|l| l == "Warning: Serial: value runs past the end of the data, kept \"strin\""),
+        "truncated value in diagnostics: got {:?}",
+        lines,
+    );
+}
+
+#[test]
+fn string_overrun() {
+    // Declared length runs 200 bytes past the end of the buffer
+    let overrun = [200, 1, b'N', b'E'];
+    let mut offset = 0;
+    let parsed = parse_value(&overrun, &mut offset, VALUE_TYPE_STRING);
+    assert!(
+        parsed.is_none() && offset == 0,
+        "overrunning string is rejected: got {:?} at offset {}",
+        parsed,
+        offset,
+    );
+    // Length prefix cut off mid-varint, and nothing left at all
+    for (name, data, start) in [
+        ("cut length prefix", &[0x80u8][..], 0),
+        ("no bytes left", &overrun[..], 4),
+    ] {
+        let mut offset = start;
+        let parsed = parse_value(data, &mut offset, VALUE_TYPE_STRING);
+        assert!(
+            parsed.is_none() && offset == start,
+            "{}: got {:?}",
+            name,
+            parsed,
+        );
+    }
+}
+
+#[test]
+fn keyless_list() {
+    let mut payload = writer::string("int(0)");
+    payload.extend(writer::string("int(1)"));
+    let body = writer::list(VALUE_TYPE_STRING, 2, &payload);
+    let parsed = read_header(&body).map(|(_, key_type, value_type, offset)| {
+        parse_dictionary_vec(&body[offset..], key_type, value_type, &mut Vec::new())
+    });
+    let expected = pairs(&[("0", "int(0)"), ("1", "int(1)")]);
+    assert!(
+        parsed.as_ref() == Some(&expected),
+        "keyless list: got {:?}",
+        parsed,
+    );
+}
+
+#[test]
+fn single_value_section() {
+    let body = writer::value(VALUE_TYPE_STRING, &writer::string(CANONICAL_VIN));
+    let parsed = parse_body(&body);
+    let serial = parsed
+        .as_ref()
+        .and_then(|p| p.iter().find(|(k, _)| k == "Serial"))
+        .map(|(_, v)| v.as_str());
+    assert!(
+        serial == Some("44271"),
+        "0xFF single-value section: got {:?}",
+        parsed,
+    );
+}
+
+#[test]
+fn known_vin() {
+    let map = decode_map();
+    let entries = parse_vin(CANONICAL_VIN, VIN_STRUCTURE);
+    let decoded = DecodedVin::new(VIN_STRUCTURE, &map, |key| {
+        entries.get(key).map_or("", String::as_str)
+    });
+    assert!(
+        decoded.iter().count() == VIN_STRUCTURE.len(),
+        "one decoded item per field: got {}",
+        decoded.iter().count(),
+    );
+    for (key, expected) in [
+        ("AssemblyPlant", "Saarlouis"),
+        ("Version", "SLX"),
+        ("Engine", "High Performance 2.0"),
+        ("Wheels", "14\" Sport"),
+        ("WindowHeater", "Heated"),
+    ] {
+        let status = decoded
+            .iter()
+            .find(|(k, ..)| *k == key)
+            .map(|(.., status)| status);
+        assert!(
+            status == Some(FieldStatus::Decoded(expected)),
+            "decode {}: expected {:?}, got {:?}",
+            key,
+            expected,
+            status,
+        );
+    }
+    assert!(
+        decoded.value("Serial") == "44271",
+        "serial: got {:?}",
+        decoded.value("Serial"),
+    );
+    assert!(
+        share_code(CANONICAL_VIN) == "0DEB-01AD",
+        "share code: got {:?}",
+        share_code(CANONICAL_VIN),
+    );
+    assert!(
+        decoded.complete_vin() == CANONICAL_VIN,
+        "complete VIN: got {:?}",
+        decoded.complete_vin(),
+    );
+}
+
+#[test]
+fn bundled_config() {
+    let parsed = toml::from_str::<VinConfig>(DEFAULT_CONFIG);
+    assert!(
+        parsed.is_ok(),
+        "bundled config parses: {:?}",
+        parsed.as_ref().err(),
+    );
+    let missing: Vec<&str> = parsed.as_ref().map_or(Vec::new(), |config| {
+        VIN_STRUCTURE
+            .iter()
+            .filter(|f| !config.descriptions.contains_key(f.key))
+            .map(|f| f.key)
+            .collect()
+    });
+    assert!(
+        missing.is_empty(),
+        "every field described: missing {:?}",
+        missing,
+    );
+    let map = decode_map();
+    let unlocated: Vec<&str> = map["AssemblyPlant"]
+        .keys()
+        .copied()
+        .filter(|code| {
+            !parsed
+                .as_ref()
+                .is_ok_and(|config| config.plant_locations.contains_key(*code))
+        })
+        .collect();
+    assert!(
+        unlocated.is_empty(),
+        "every plant located: missing {:?}",
+        unlocated,
+    );
+}
+
+#[test]
+fn code_lengths() {
+    let config: Result<VinConfig, _> = toml::from_str(
+        "[codes.Engine]\nNX = \"fits\"\nN = \"too short\"\n\
+         [codes.Wheels]\nZZZ = \"too long\"\n\
+         [codes.Nope]\nA = \"unknown field\"\n",
+    );
+    let warnings = config.map(|c| validate(&c)).unwrap_or_default();
+    let expected = [
+        "[codes.Engine]: code \"N\" has 1 characters but the field holds 2",
+        "[codes.Nope]: no such field",
+        "[codes.Wheels]: code \"ZZZ\" has 3 characters but the field holds 1",
+    ];
+    assert!(
+        warnings == expected,
+        "code length warnings: got {:?}",
+        warnings,
+    );
+}
+
+#[test]
+fn unmapped_codes() {
+    let map = decode_map();
+    for (key, val, expected) in [
+        ("AssemblyPlant", "Z", "!! [UNDOCUMENTED] !!"),
+        ("AssemblyPlant", "7", "!! [INVALID] !!"),
+        ("Suspension", "7", "!! [UNDOCUMENTED] !!"),
+        ("Wheels", "*", "!! [INVALID] !!"),
+        ("Engine", "NX", "!! [UNDOCUMENTED] !!"),
+        ("Engine", "N1", "!! [INVALID] !!"),
+    ] {
+        let field = VIN_STRUCTURE.iter().find(|f| f.key == key);
+        let status = field.map_or("", |f| field_status(&map, f, val));
+        assert!(
+            status == expected,
+            "{} {}: expected {:?}, got {:?}",
+            key,
+            val,
+            expected,
+            status,
+        );
+    }
+}
+
+#[test]
+fn delimited_vin() {
+    let piped = "U|C|B|B|G|N|Y|44271|1|NE|B|S|B|K|C|N|J|M|2|8|A|-|4|B";
+    let parsed = parse_delimited_vin(&vin_tokens(piped), VIN_STRUCTURE);
+    assert!(
+        parsed.as_ref().ok() == Some(&parse_vin(CANONICAL_VIN, VIN_STRUCTURE)),
+        "pipe-delimited VIN: got {:?}",
+        parsed,
+    );
+    let shifted = "U|C|B|B|G|N|Y|4427|1|NE|B|S|B|K|C|N|J|M|2|8|A|-|4|B";
+    let parsed = parse_delimited_vin(&vin_tokens(shifted), VIN_STRUCTURE);
+    let expected = "Field 8 (Serial) should be 5 characters, got \"4427\"";
+    assert!(
+        parsed.as_ref().err().map(String::as_str) == Some(expected),
+        "misaligned token: got {:?}",
+        parsed,
+    );
+}
+
+#[test]
+fn reverse_lookup() {
+    let map = decode_map();
+    for (field, label, expected) in [
+        ("AssemblyPlant", "Rheine", Some("K")),
+        ("Month", "02", Some("K")),
+        ("Wheels", "15\" Alloy", None),
+        ("Nope", "Rheine", None),
+    ] {
+        let code = encode_field(&map, field, label);
+        assert!(
+            code == expected,
+            "encode {} {}: expected {:?}, got {:?}",
+            field,
+            label,
+            expected,
+            code,
+        );
+    }
+
+    let mut ambiguous = DecodeMap::new();
+    ambiguous.insert(
+        "Seats",
+        [("8", "Standard"), ("3", "Standard"), ("B", "Bucket Style")]
+            .into_iter()
+            .collect(),
+    );
+    let code = encode_field(&ambiguous, "Seats", "Standard");
+    assert!(
+        code == Some("3"),
+        "ambiguous label takes first code: got {:?}",
+        code,
+    );
+}
+
+#[test]
+fn vin_in_text() {
+    let map = decode_map();
+    let post = format!(
+        "Bought my Rivett today!! VIN is {}, anyone know what SLX means? ABCDEFGHIJKLMNOPQRSTUVWXYZABC",
+        CANONICAL_VIN
+    );
+    let found = find_vin_in_text(&post, VIN_STRUCTURE, &map).map(|(vin, _)| vin);
+    assert!(
+        found.as_deref() == Some(CANONICAL_VIN),
+        "VIN in text: got {:?}",
+        found,
+    );
+    let found = find_vin_in_text("no vin in this message at all", VIN_STRUCTURE, &map);
+    assert!(found.is_none(), "no VIN in text: got {:?}", found,);
+}
+
+#[test]
+fn template() {
+    let template = export::render_template();
+    let rows = template.lines().filter(|l| l.starts_with("| ")).count();
+    let expected: usize = VEHICLE_MODELS.iter().map(|m| m.structure.len() + 1).sum();
+    assert!(
+        rows == expected,
+        "template lists every field: expected {} rows, got {}",
+        expected,
+        rows,
+    );
+    assert!(
+        template.contains("| Assembly Plant |  | A = Dagenham;"),
+        "template lists options: Assembly Plant options missing",
+    );
+}
+
+#[test]
+fn vin_builder() {
+    let map = decode_map();
+    let fields = parse_vin(CANONICAL_VIN, VIN_STRUCTURE);
+    let filled = |builder| {
+        VIN_STRUCTURE
+            .iter()
+            .fold(builder, |b: DecodedVinBuilder, f| {
+                b.set(f.key, &fields[f.key])
+            })
+    };
+    let vin = filled(DecodedVin::builder(VIN_STRUCTURE, &map)).build_vin();
+    assert!(
+        vin.as_deref() == Ok(CANONICAL_VIN),
+        "builder assembles VIN: got {:?}",
+        vin,
+    );
+    let decoded = filled(DecodedVin::builder(VIN_STRUCTURE, &map)).build();
+    let version = decoded.as_ref().map(|d| d.value("Version"));
+    assert!(version == Ok("G"), "builder decodes VIN: got {:?}", version,);
+
+    let errors = filled(DecodedVin::builder(VIN_STRUCTURE, &map))
+        .set("Version", "Q")
+        .set("Serial", "123")
+        .set("Nope", "A")
+        .build_vin();
+    let expected = vec![
+        "Version has no code \"Q\"".to_string(),
+        "Serial needs exactly 5 character(s), got \"123\"".to_string(),
+        "Unknown field Nope".to_string(),
+    ];
+    assert!(errors == Err(expected), "builder errors: got {:?}", errors,);
+    let lenient = filled(DecodedVin::builder(VIN_STRUCTURE, &map))
+        .strict(false)
+        .set("Version", "Q")
+        .build_vin();
+    assert!(lenient.is_ok(), "lenient builder: got {:?}", lenient,);
+    let missing = DecodedVin::builder(VIN_STRUCTURE, &map)
+        .build_vin()
+        .map_err(|e| e.len());
+    assert!(
+        missing == Err(VIN_STRUCTURE.len()),
+        "builder reports unset fields: got {:?}",
+        missing,
+    );
+}
+
+#[test]
+fn code_normalization() {
+    let map = decode_map();
+    let engine = VIN_STRUCTURE.iter().find(|f| f.key == "Engine");
+    for (raw, case_sensitive, expected) in [
+        (" na", false, "Standard 2.0"),
+        ("Ne ", false, "High Performance 2.0"),
+        (" NA\t", true, "Standard 2.0"),
+        ("na", true, "!! [INVALID] !!"),
+    ] {
+        let code = normalize_code(raw, case_sensitive);
+        let status = engine.map_or("", |f| field_status(&map, f, &code));
+        assert!(
+            status == expected,
+            "Engine {:?} (case-sensitive: {}): expected {:?}, got {:?}",
+            raw,
+            case_sensitive,
+            expected,
+            status,
+        );
+    }
+    let padded = format!(" {} \n", CANONICAL_VIN.to_lowercase());
+    let decoded = decode_vin(&padded, &map);
+    let engine = decoded.status("Engine");
+    assert!(
+        decoded.complete_vin() == CANONICAL_VIN
+            && engine == FieldStatus::Decoded("High Performance 2.0"),
+        "decode_vin normalizes its input: got {} with Engine {:?}",
+        decoded.complete_vin(),
+        engine,
+    );
+}
+
+#[test]
+fn export_formats() {
+    let map = decode_map();
+    let fields = parse_vin(CANONICAL_VIN, VIN_STRUCTURE);
+    let decoded = DecodedVin::new(VIN_STRUCTURE, &map, |key| {
+        fields.get(key).map_or("", String::as_str)
+    });
+    let json = export::ExportFormat::Json.render(&decoded);
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap_or_default();
+    let field = |key: &str| {
+        value["fields"]
+            .as_array()
+            .and_then(|fields| fields.iter().find(|f| f["key"] == key))
+            .cloned()
+            .unwrap_or_default()
+    };
+    assert!(
+        value["complete_vin"] == CANONICAL_VIN
+            && field("Engine")
+                == serde_json::json!({
+                    "key": "Engine",
+                    "name": "Engine",
+                    "code": "NE",
+                    "status": "decoded",
+                    "decoded": "High Performance 2.0",
+                })
+            && field("Wheels")["decoded"] == "14\" Sport",
+        "JSON export: got {}",
+        json,
+    );
+    assert!(
+        field("Serial")["code"] == "44271"
+            && field("Serial")["status"] == "raw"
+            && field("Serial")["decoded"].is_null()
+            && value["source"].is_null(),
+        "JSON nulls for raw values: got {}",
+        json,
+    );
+    let schema_keys: Vec<&str> = export::JSON_SCHEMA
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix('"')?.split('"').next())
+        .collect();
+    let engine = field("Engine");
+    let mut json_keys: Vec<&str> = value
+        .as_object()
+        .into_iter()
+        .flat_map(|o| o.keys())
+        .chain(engine.as_object().into_iter().flat_map(|o| o.keys()))
+        .map(String::as_str)
+        .collect();
+    let mut schema_sorted = schema_keys.clone();
+    schema_sorted.sort_unstable();
+    json_keys.sort_unstable();
+    assert!(
+        schema_sorted == json_keys,
+        "JSON_SCHEMA names every key: schema {:?}, JSON {:?}",
+        schema_keys,
+        json_keys,
+    );
+    let manual = DecodedVin::new(VIN_STRUCTURE, &map, |key| {
+        fields.get(key).map_or("", String::as_str)
+    })
+    .with_source(DecodeSource::Manual);
+    let json = export::ExportFormat::Json.render(&manual);
+    assert!(
+        json.contains("\"source\": \"manual\""),
+        "JSON source: got {}",
+        json,
+    );
+    let csv = export::ExportFormat::Csv.render(&decoded);
+    let rows = csv.lines().count();
+    assert!(
+        rows == VIN_STRUCTURE.len() + 1
+            && csv.starts_with("Field,Value,Decoded\n")
+            && csv.contains("\nWheels,4,\"14\"\" Sport\"\n")
+            && csv.contains("\nAssembly Plant,C,Saarlouis\n"),
+        "CSV export: got {}",
+        csv,
+    );
+}
+
+#[test]
+fn gt_combinations() {
+    let canonical = parse_vin(CANONICAL_VIN, VIN_STRUCTURE);
+    let warnings = check_combinations(|key| canonical.get(key).map_or("", String::as_str));
+    assert!(
+        warnings.is_empty(),
+        "canonical VIN is plausible: got {:?}",
+        warnings,
+    );
+    let mut gt = canonical.clone();
+    gt.insert("Version".to_string(), "P".to_string());
+    gt.insert("ColorsBody".to_string(), "C".to_string());
+    gt.insert("InteriorTrim".to_string(), "A".to_string());
+    let warnings = check_combinations(|key| gt.get(key).map_or("", String::as_str));
+    let expected = [
+        "The GT was never painted in a base-model color",
+        "The GT was never fitted with the plain black interior",
+    ];
+    assert!(
+        warnings == expected,
+        "GT base-only options: got {:?}",
+        warnings,
+    );
+}
+
+#[test]
+fn model_bodies() {
+    let mut fields = parse_vin(CANONICAL_VIN, VIN_STRUCTURE);
+    fields.insert("Body".to_string(), "K".to_string());
+    let warnings = check_combinations(|key| fields.get(key).map_or("", String::as_str));
+    assert!(
+        warnings == ["This body type was never built for the model"],
+        "body not built for the model: got {:?}",
+        warnings,
+    );
+    // Unknown models have no body list to check against
+    fields.insert("Model".to_string(), "Z".to_string());
+    let warnings = check_combinations(|key| fields.get(key).map_or("", String::as_str));
+    assert!(
+        warnings.is_empty(),
+        "body of an unknown model: got {:?}",
+        warnings,
+    );
+}
+
+#[test]
+fn vin_alignments() {
+    let map = decode_map();
+    // Serial typed with four digits
+    let short = CANONICAL_VIN.replacen("44271", "4427", 1);
+    let list = alignments(&short, VIN_STRUCTURE, &map);
+    let best: Vec<_> = list
+        .iter()
+        .filter(|a| a.score == VIN_STRUCTURE.len())
+        .map(|a| a.edit.as_str())
+        .collect();
+    assert!(
+        list.len() == short.len() + 1
+            && best.first() == Some(&"insert \"0\" at position 8")
+            && best.len() == 5,
+        "alignment inserts into the serial: got {:?}",
+        best,
+    );
+    let long = CANONICAL_VIN.replacen("44271", "442711", 1);
+    let list = alignments(&long, VIN_STRUCTURE, &map);
+    let best = list.iter().max_by_key(|a| a.score).map(|a| a.vin.as_str());
+    assert!(
+        best == Some(CANONICAL_VIN),
+        "alignment removes from the serial: got {:?}",
+        best,
+    );
+    assert!(
+        alignments("UCB", VIN_STRUCTURE, &map).is_empty(),
+        "no alignments for far-off lengths: got alignments",
+    );
+}
+
+#[test]
+fn corrupt_entry() {
+    let mut payload = writer::string("Engine");
+    payload.extend(writer::string("NE"));
+    let body = writer::dictionary(VALUE_TYPE_STRING, 1, &payload);
+    let mut buffer = writer::entry("Bodywork", &[1, 2, 3]);
+    // Claims a body far longer than the rest of the file
+    let corrupt_at = buffer.len();
+    buffer.extend(writer::entry("Broken", &[0; 8]));
+    buffer[corrupt_at + 2 + "Broken".len() + 3] = 0x7F;
+    buffer.extend(writer::entry(writer::VINGEN4_TAG, &body));
+    let mut diagnostics = ParseDiagnostics::default();
+    let parsed = parse_vingen4_bytes(&buffer, &mut diagnostics);
+    assert!(
+        parsed == Some(pairs(&[("Engine", "NE")])) && diagnostics.malformed_entries == 1,
+        "VIN section after a corrupt entry: got {:?}, {} malformed",
+        parsed,
+        diagnostics.malformed_entries,
+    );
+
+    // The corrupt entry first, read the way a loaded file is
+    let (result, diagnostics) = parse_carparts(buffer[corrupt_at..].to_vec(), "carparts.txt");
+    assert!(
+        result.as_ref() == Ok(&vec![pairs(&[("Engine", "NE")])])
+            && diagnostics.malformed_entries == 1,
+        "loaded file starting with a corrupt entry: got {:?}, {} malformed",
+        result,
+        diagnostics.malformed_entries,
+    );
+}
+
+/// Every typed code matches the decode table, and the table has no extra codes
+fn assert_code_enum<T: FieldCode + std::fmt::Debug>(map: &DecodeMap) {
+    let table = map.get(T::KEY);
+    for &code in T::ALL {
+        let label = table.and_then(|t| t.get(code.to_code())).copied();
+        assert!(
+            label == Some(code.display()) && T::from_code(code.to_code()).is_some(),
+            "{} {:?}: table has {:?} for {:?}",
+            T::KEY,
+            code,
+            label,
+            code.to_code(),
+        );
+    }
+    let extra: Vec<&str> = table.map_or(Vec::new(), |t| {
+        t.keys()
+            .copied()
+            .filter(|c| T::from_code(c).is_none())
+            .collect()
+    });
+    assert!(
+        extra.is_empty(),
+        "{} fully typed: untyped codes {:?}",
+        T::KEY,
+        extra,
+    );
+}
+
+#[test]
+fn typed_codes() {
+    let map = decode_map();
+    assert_code_enum::<Version>(&map);
+    assert_code_enum::<Drive>(&map);
+    assert_code_enum::<Engine>(&map);
+    assert_code_enum::<Gearbox>(&map);
+    assert_code_enum::<Year>(&map);
+
+    let fields = parse_vin(CANONICAL_VIN, VIN_STRUCTURE);
+    let decoded = DecodedVin::new(VIN_STRUCTURE, &map, |key| {
+        fields.get(key).map_or("", String::as_str)
+    });
+    let typed = (decoded.get::<Version>(), decoded.get::<Engine>());
+    assert!(
+        typed == (Some(Version::Slx), Some(Engine::HighPerformance)),
+        "typed codes of a VIN: got {:?}",
+        typed,
+    );
+}
+
+#[test]
+fn export_notes() {
+    let map = decode_map();
+    let fields = parse_vin(CANONICAL_VIN, VIN_STRUCTURE);
+    let decoded = DecodedVin::new(VIN_STRUCTURE, &map, |key| {
+        fields.get(key).map_or("", String::as_str)
+    });
+    let json = export::ExportFormat::Json.render(&decoded);
+    assert!(
+        json.contains("\"notes\": null,"),
+        "JSON without notes: got {}",
+        json,
+    );
+    let decoded = decoded.with_notes(" project car,\n\"needs\" paint ");
+    let json = export::ExportFormat::Json.render(&decoded);
+    assert!(
+        json.contains("\"notes\": \"project car,\\n\\\"needs\\\" paint\","),
+        "JSON notes: got {}",
+        json,
+    );
+    let text = export::ExportFormat::Text.render(&decoded);
+    assert!(
+        text.ends_with("\nNotes:\nproject car,\n\"needs\" paint\n"),
+        "text notes: got {}",
+        text,
+    );
+}
+
+#[test]
+fn option_search() {
+    let map = decode_map();
+    let hits = |query: &str| -> Vec<(&str, &str)> {
+        search_options(VIN_STRUCTURE, &map, query)
+            .into_iter()
+            .map(|(field, code, _)| (field.key, code))
+            .collect()
+    };
+    let found = hits("tachometer");
+    assert!(
+        found == [("InstrumentPanel", "M")],
+        "option search substring: got {:?}",
+        found,
+    );
+    let found = hits("hi prf");
+    assert!(
+        found == [("Engine", "NE")],
+        "option search fuzzy: got {:?}",
+        found,
+    );
+    assert!(hits("  ").is_empty(), "blank option search: got hits",);
+}
+
+#[test]
+fn deep_link_round_trip() {
+    let link = deep_link(CANONICAL_VIN);
+    let expected = format!("mwcvin://decode/{}", CANONICAL_VIN);
+    assert!(
+        link.as_deref() == Some(&*expected),
+        "deep link: got {:?}",
+        link,
+    );
+    let vin = link.as_deref().and_then(vin_from_deep_link);
+    assert!(
+        vin == Some(CANONICAL_VIN),
+        "deep link round trip: got {:?}",
+        vin,
+    );
+    for bad in ["UCBBG", "UCBBGNY442711NEBSBKCNJM28A-4B?x=1", ""] {
+        assert!(
+            deep_link(bad).is_none(),
+            "no deep link for {:?}: got a link",
+            bad,
+        );
+    }
+    let vin = vin_from_deep_link("mwcvin://decode/../etc");
+    assert!(vin.is_none(), "deep link rejects paths: got {:?}", vin,);
+}
+
+#[test]
+fn wiki_export() {
+    let map = decode_map();
+    let fields = parse_vin(CANONICAL_VIN, VIN_STRUCTURE);
+    let decoded = DecodedVin::new(VIN_STRUCTURE, &map, |key| {
+        fields.get(key).map_or("", String::as_str)
+    });
+    let wiki = export::ExportFormat::Wiki.render(&decoded);
+    assert!(
+        wiki.starts_with(
+            "{| class=\"wikitable\"\n|+ VIN UCBBGNY442711NEBSBKCNJM28A-4B (share code ",
+        ) && wiki.ends_with("\n|}\n"),
+        "wiki table frame: {}",
+        wiki.clone(),
+    );
+    assert!(
+        wiki.contains("|-\n| Version || G || SLX\n"),
+        "wiki table row: {}",
+        wiki.clone(),
+    );
+    let rows = wiki.matches("\n|-\n").count();
+    assert!(
+        rows == VIN_STRUCTURE.len(),
+        "wiki row per field: {} rows",
+        rows,
+    );
+
+    let mut odd = fields.clone();
+    odd.insert("Version".to_string(), "|[".to_string());
+    let decoded = DecodedVin::new(VIN_STRUCTURE, &map, |key| {
+        odd.get(key).map_or("", String::as_str)
+    });
+    let wiki = export::ExportFormat::Wiki.render(&decoded);
+    assert!(
+        wiki.contains("| Version || &#124;&#91; || &#33;&#33; &#91;INVALID&#93; &#33;&#33;"),
+        "wiki escapes markup: {}",
+        wiki.clone(),
+    );
+}
+
+#[test]
+fn key_value_export() {
+    let map = decode_map();
+    let fields = parse_vin(CANONICAL_VIN, VIN_STRUCTURE);
+    let decoded = DecodedVin::new(VIN_STRUCTURE, &map, |key| {
+        fields.get(key).map_or("", String::as_str)
+    });
+    let text = export::ExportFormat::KeyValue.render(&decoded);
+    let lines: Vec<&str> = text.lines().collect();
+    assert!(
+        lines.first() == Some(&"VIN=UCBBGNY442711NEBSBKCNJM28A-4B")
+            && lines.contains(&"Version=SLX")
+            && lines.contains(&"Serial=44271")
+            && lines.len() == VIN_STRUCTURE.len() + 1,
+        "key=value lines: {}",
+        text.clone(),
+    );
+    let extensions: Vec<_> = export::ExportFormat::ALL
+        .iter()
+        .map(|format| format.extension())
+        .collect();
+    let distinct = extensions
+        .iter()
+        .enumerate()
+        .all(|(i, ext)| !extensions[..i].contains(ext));
+    assert!(
+        distinct,
+        "bundle files don't overwrite each other: got {:?}",
+        extensions,
+    );
+}
+
+#[test]
+fn parse_panic() {
+    let result: Result<(), String> = guard_parse(|| panic!("offset out of range"));
+    assert!(
+        result == Err(PARSE_PANIC_ERROR.to_string()),
+        "parser panic becomes an error: got {:?}",
+        result,
+    );
+    let result = guard_parse(|| Ok::<_, String>(1));
+    assert!(
+        result == Ok(1),
+        "guarded parse passes results through: got {:?}",
+        result,
+    );
+}
+
+#[test]
+fn gzip_fixture() {
+    let plain = include_bytes!("../example_carparts.txt");
+    let compressed = include_bytes!("../example_carparts.txt.gz");
+    let unpacked = gunzip_if_compressed(compressed.to_vec());
+    assert!(
+        unpacked.as_deref() == Ok(plain.as_slice()),
+        "gzip fixture decompresses: got {:?}",
+        unpacked.map(|b| b.len()),
+    );
+    let same = gunzip_if_compressed(plain.to_vec());
+    assert!(
+        same.as_deref() == Ok(plain.as_slice()),
+        "plain file passes through: changed",
+    );
+    let truncated = gunzip_if_compressed(compressed[..compressed.len() / 2].to_vec());
+    assert!(
+        truncated.is_err(),
+        "truncated gzip is an error: decompressed",
+    );
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("example_carparts.txt.gz");
+    let entries = parse_vingen4_file(&path);
+    assert!(
+        entries.as_ref().is_some_and(|entries| !entries.is_empty()),
+        "parse_vingen4_file reads a .txt.gz: got {:?}",
+        entries,
+    );
+}
+
+#[test]
+fn confidence() {
+    let map = decode_map();
+    let fields = parse_vin(CANONICAL_VIN, VIN_STRUCTURE);
+    let confidence = |fields: &HashMap<String, String>| {
+        let decoded = DecodedVin::new(VIN_STRUCTURE, &map, |key| {
+            fields.get(key).map_or("", String::as_str)
+        });
+        decode_confidence(&decoded)
+    };
+    let score = confidence(&fields);
+    assert!(score == 100, "canonical VIN confidence: got {}", score,);
+    let mut odd = fields.clone();
+    // Unknown wheels, and a GT in a base-model color
+    odd.insert("Wheels".to_string(), "?".to_string());
+    odd.insert("Version".to_string(), "P".to_string());
+    odd.insert("ColorsBody".to_string(), "C".to_string());
+    let score = confidence(&odd);
+    assert!(score == 82, "confidence penalties: got {}", score,);
+    let score = confidence(&HashMap::new());
+    assert!(score == 0, "confidence floor: got {}", score,);
+    let mut redacted = fields.clone();
+    redacted.insert("Serial".to_string(), REDACTED_SERIAL.to_string());
+    let score = confidence(&redacted);
+    assert!(
+        score == 100,
+        "redacted serial keeps the confidence: got {}",
+        score,
+    );
+    let mut no_serial = fields.clone();
+    no_serial.insert("Serial".to_string(), String::new());
+    let decoded = DecodedVin::new(VIN_STRUCTURE, &map, |key| {
+        no_serial.get(key).map_or("", String::as_str)
+    });
+    let counted = decoded_fields(&decoded);
+    assert!(
+        counted == VIN_STRUCTURE.len() - 1,
+        "empty serial is not decoded: got {}",
+        counted,
+    );
+}
+
+#[test]
+fn dropped_bytes() {
+    let compressed = include_bytes!("../example_carparts.txt.gz");
+    let (result, _) = parse_carparts(compressed.to_vec(), "carparts.txt.gz");
+    assert!(
+        result.as_ref().is_ok_and(|vehicles| !vehicles.is_empty()),
+        "dropped bytes parse without a file: got {:?}",
+        result.as_ref().map(Vec::len),
+    );
+    let (result, _) = parse_carparts(b"not a save".to_vec(), "notes.txt");
+    assert!(
+        result.as_ref().is_err_and(|e| e.contains("notes.txt")),
+        "dropped bytes error names the file: got {:?}",
+        result,
+    );
+}
+
+#[test]
+fn section_inspector() {
+    let mut buffer = writer::entry(
+        "PaintShop",
+        &writer::list(VALUE_TYPE_INT32, 2, &[7, 0, 0, 0, 9, 0, 0, 0]),
+    );
+    let payload = [writer::string("Gear"), writer::string("4")].concat();
+    buffer.extend(writer::entry(
+        "Settings",
+        &writer::dictionary(VALUE_TYPE_STRING, 1, &payload),
+    ));
+    buffer.extend(writer::entry(
+        "Cash",
+        &writer::value(VALUE_TYPE_INT32, &[100, 0, 0, 0]),
+    ));
+    buffer.extend(writer::vingen4(&split_vin_entries(CANONICAL_VIN)));
+    let (_, diagnostics) = parse_carparts(buffer, "carparts.txt");
+    let tags: Vec<&str> = diagnostics
+        .sections
+        .iter()
+        .map(|(tag, _)| tag.as_str())
+        .collect();
+    assert!(
+        tags == ["PaintShop", "Settings", "Cash", "VINGen4"],
+        "every section is listed: got {:?}",
+        tags,
+    );
+    let sections: Vec<_> = (0..3)
+        .map(|i| {
+            diagnostics
+                .section_body(i)
+                .map_or(Err("no body".to_string()), parse_section)
+        })
+        .collect();
+    let expected = [
+        Ok(pairs(&[("0", "7"), ("1", "9")])),
+        Ok(pairs(&[("Gear", "4")])),
+        Ok(pairs(&[("value", "100")])),
+    ];
+    assert!(sections == expected, "sections parse: got {:?}", sections,);
+    let cut = parse_section(&[0x52]);
+    assert!(cut.is_err(), "cut-off section header: got {:?}", cut,);
+}
+
+#[test]
+fn section_order_by_key() {
+    let entries = pairs(&[("b", "1"), ("A", "2"), ("10", "3"), ("2", "4")]);
+    let keys = |by_key| -> Vec<&str> {
+        section_order(&entries, by_key)
+            .iter()
+            .map(|(k, _)| k.as_str())
+            .collect()
+    };
+    assert!(
+        keys(false) == ["b", "A", "10", "2"],
+        "section file order: got {:?}",
+        keys(false),
+    );
+    // Word keys sort before indices
+    assert!(
+        keys(true) == ["A", "b", "2", "10"],
+        "section key order: got {:?}",
+        keys(true),
+    );
+}
+
+#[test]
+fn float_precision() {
+    let cases = [
+        ("3.439999", 2, Some("3.44")),
+        ("3.439999", 0, Some("3")),
+        (" 4.1 ", 3, Some("4.100")),
+        ("NaN", 2, None),
+        ("abc", 2, None),
+    ];
+    for (value, decimals, expected) in cases {
+        let got = format_float(value, decimals);
+        assert!(
+            got.as_deref() == expected,
+            "float {:?} at {} decimals: got {:?}",
+            value,
+            decimals,
+            got,
+        );
+    }
+    let rounded = round_typed_float("float(3.439999)", 2);
+    let other = round_typed_float("int(3)", 2);
+    assert!(
+        rounded == "float(3.44)" && other == "int(3)",
+        "typed float rounding: got {:?} and {:?}",
+        rounded,
+        other,
+    );
+}
+
+#[test]
+fn decode_vin_from_table() {
+    let map = decode_map();
+    let decoded = decode_vin(CANONICAL_VIN, &map);
+    let fields = parse_vin(CANONICAL_VIN, VIN_STRUCTURE);
+    let expected = DecodedVin::new(VIN_STRUCTURE, &map, |key| {
+        fields.get(key).map_or("", String::as_str)
+    });
+    let same = decoded.iter().eq(expected.iter());
+    assert!(
+        same,
+        "decode_vin matches the field split: got {:?}",
+        decoded,
+    );
+    assert!(
+        decoded.status("Version") == FieldStatus::Decoded("SLX")
+            && decoded.status("Serial") == FieldStatus::Raw
+            && decoded.complete_vin() == CANONICAL_VIN,
+        "decode_vin statuses: got {:?}",
+        decoded,
+    );
+    let short = decode_vin("UCBBG", &map);
+    assert!(
+        short.value("Version") == "G" && short.value("Year").is_empty(),
+        "short VIN leaves later fields empty: got {:?}",
+        short,
+    );
+}
+
+#[test]
+fn code_suggestions() {
+    let map = decode_map();
+    let field = |key: &str| VIN_STRUCTURE.iter().find(|f| f.key == key).unwrap();
+    let cases = [
+        ("Gearbox", "8", Some(("B", "4-spd Manual"))),
+        ("Version", "6", Some(("G", "SLX"))),
+        ("Version", "X", None),
+        ("Engine", "NF", None),
+        ("Model", "X", None),
+        ("Model", "8", Some(("B", "Rivett"))),
+    ];
+    for (key, val, expected) in cases {
+        let got = suggest_code(&map, field(key), val);
+        assert!(
+            got == expected,
+            "suggestion for {} {}: got {:?}",
+            key,
+            val,
+            got,
+        );
+    }
+    let decoded = decode_vin(&CANONICAL_VIN.replacen("UCBBG", "UCBB6", 1), &map);
+    assert!(
+        decoded.suggestion("Version") == Some(("G", "SLX")) && decoded.suggestion("Body").is_none(),
+        "decode carries the suggestion: got {:?}",
+        decoded.suggestion("Version"),
+    );
+}
@@ -0,0 +1,83 @@
+//! Writer side of the carparts.txt format, for building parser fixtures
+//!
+//! ```text
+//! entry:      7E <tag len: u8> <tag> <body len: u32 LE> <body>
+//! dictionary: 52 FF <key type: u32 LE> <value type: u32 LE> 00 00 <count: u32 LE> (key value)*
+//! list:       53 FF <value type: u32 LE> 00 <count: u32 LE> value*
+//! value:      FF <value type: u32 LE> value
+//! string:     <7-bit encoded length> <UTF-8 bytes>
+//! ```
+
+use crate::{
+    CONTAINER_TYPE_DICTIONARY, CONTAINER_TYPE_LIST, HX_NO_CONTAINER, HX_START_ENTRY,
+    VALUE_TYPE_STRING,
+};
+
+/// Tag of the entry holding the VIN dictionary
+pub const VINGEN4_TAG: &str = "VINGen4";
+
+/// String with its 7-bit encoded length prefix (as .NET's BinaryWriter writes it)
+pub fn string(s: &str) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut len = s.len();
+    loop {
+        let byte = (len & 0x7F) as u8;
+        len >>= 7;
+        if len == 0 {
+            out.push(byte);
+            break;
+        }
+        out.push(byte | 0x80);
+    }
+    out.extend_from_slice(s.as_bytes());
+    out
+}
+
+/// Wrap a body into a tagged entry
+pub fn entry(tag: &str, body: &[u8]) -> Vec<u8> {
+    let mut out = vec![HX_START_ENTRY, tag.len() as u8];
+    out.extend_from_slice(tag.as_bytes());
+    out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    out.extend_from_slice(body);
+    out
+}
+
+/// Dictionary body with string keys, `payload` holds `count` encoded key/value pairs
+pub fn dictionary(value_type: u32, count: u32, payload: &[u8]) -> Vec<u8> {
+    let mut out = vec![CONTAINER_TYPE_DICTIONARY, HX_NO_CONTAINER];
+    out.extend_from_slice(&VALUE_TYPE_STRING.to_le_bytes());
+    out.extend_from_slice(&value_type.to_le_bytes());
+    out.extend_from_slice(&[0, 0]);
+    out.extend_from_slice(&count.to_le_bytes());
+    out.extend_from_slice(payload);
+    out
+}
+
+/// Keyless list body, `payload` holds `count` encoded values
+pub fn list(value_type: u32, count: u32, payload: &[u8]) -> Vec<u8> {
+    let mut out = vec![CONTAINER_TYPE_LIST, HX_NO_CONTAINER];
+    out.extend_from_slice(&value_type.to_le_bytes());
+    out.push(0);
+    out.extend_from_slice(&count.to_le_bytes());
+    out.extend_from_slice(payload);
+    out
+}
+
+/// Single-value body holding one encoded value
+pub fn value(value_type: u32, payload: &[u8]) -> Vec<u8> {
+    let mut out = vec![HX_NO_CONTAINER];
+    out.extend_from_slice(&value_type.to_le_bytes());
+    out.extend_from_slice(payload);
+    out
+}
+
+/// Complete VINGen4 entry with string keys and string values
+pub fn vingen4(entries: &[(String, String)]) -> Vec<u8> {
+    let mut payload = Vec::new();
+    for (key, value) in entries {
+        payload.extend(string(key));
+        payload.extend(string(value));
+    }
+    let body = dictionary(VALUE_TYPE_STRING, entries.len() as u32, &payload);
+    entry(VINGEN4_TAG, &body)
+}